@@ -0,0 +1,573 @@
+mod validate;
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+use asai::encoding::EncodingSource;
+use asai::matroska::Matroska;
+use asai::lint::{Location, LintConfig, Rule, Severity};
+use asai::structure::event::{format_timestamp, EventKey};
+use asai::structure::srt::parse_srt;
+use asai::structure::timing::TimingOptions;
+use asai::video::keyframes::Keyframes;
+use asai::video::FrameTimes;
+use asai::structure::format::ColumnOrder;
+use asai::structure::{Ass, WrapStyle};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde_json::json;
+
+#[derive(Parser)]
+#[command(name = "asai", version, about = "Tool for .ass subtitle files")]
+struct Cli {
+    /// Print machine-readable JSON instead of text.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print script info and basic counts.
+    Info { input: Option<PathBuf> },
+    /// Report problems with line numbers. Exits with 1 if there are errors.
+    Validate { input: Option<PathBuf> },
+    /// Check script for quality problems. Exits with 1 if there are errors.
+    Lint {
+        input: Option<PathBuf>,
+        /// Rule to skip, like `cps` or `overlap`. Can be repeated.
+        #[arg(long, value_name = "RULE")]
+        disable: Vec<String>,
+        /// Maximum characters per second.
+        #[arg(long, default_value = "25")]
+        max_cps: f64,
+    },
+    /// Apply automatic fixes for lint findings and write fixed script.
+    Fix {
+        input: Option<PathBuf>,
+        /// Rule not to fix, like `unsorted-events`. Can be repeated.
+        #[arg(long, value_name = "RULE")]
+        disable: Vec<String>,
+    },
+    /// Print detected character encoding of a script.
+    Encoding { input: Option<PathBuf> },
+    /// Remove redundant override tags without changing rendered output.
+    Optimize { input: Option<PathBuf> },
+    /// Add lead-in/out and link adjacent lines, like Aegisub's timing post-processor.
+    Timing {
+        input: Option<PathBuf>,
+        /// Milliseconds added before each line.
+        #[arg(long, default_value = "0")]
+        lead_in: u64,
+        /// Milliseconds added after each line.
+        #[arg(long, default_value = "0")]
+        lead_out: u64,
+        /// Close gaps between lines up to this many milliseconds.
+        #[arg(long, default_value = "0")]
+        link: u64,
+        /// Where linked lines meet: 0 is the end of the first line, 1 is the start of the second.
+        #[arg(long, default_value = "0.5")]
+        bias: f64,
+        /// Style to process. Can be repeated, all styles if not given.
+        #[arg(long)]
+        style: Vec<String>,
+        /// Snap to scene changes from a keyframes file.
+        #[arg(long)]
+        keyframes: Option<PathBuf>,
+        #[command(flatten)]
+        video: VideoArgs,
+    },
+    /// Round event times to frame boundaries. Lines shorter than a frame are reported.
+    Snap {
+        input: Option<PathBuf>,
+        #[command(flatten)]
+        video: VideoArgs,
+    },
+    /// Move all events in time, like `1.5`, `-0:00:02.00` or `+250ms`.
+    Shift {
+        #[arg(allow_hyphen_values = true)]
+        offset: String,
+        input: Option<PathBuf>,
+    },
+    /// Convert between formats. Input format is detected from content.
+    Convert {
+        #[arg(long, value_enum, default_value = "ass")]
+        to: Format,
+        input: Option<PathBuf>,
+    },
+    /// Rewrite script in canonical form.
+    Fmt {
+        /// Keep event columns in the order of the script's `Format` line.
+        #[arg(long)]
+        preserve_columns: bool,
+        input: Option<PathBuf>,
+    },
+    /// Write fonts embedded into script to a directory.
+    ExtractFonts {
+        input: Option<PathBuf>,
+        #[arg(long, short, default_value = ".")]
+        output: PathBuf,
+    },
+    /// Extract ASS track from a Matroska file, with attached fonts.
+    ExtractMkv {
+        input: PathBuf,
+        /// Index of the track among ASS tracks of the file.
+        #[arg(long, default_value = "0")]
+        track: usize,
+        /// List ASS tracks instead.
+        #[arg(long)]
+        list: bool,
+    },
+    /// Print statistics about events.
+    Stats { input: Option<PathBuf> },
+}
+
+/// Frame rate of the video, needed to convert frame numbers to times.
+#[derive(Args)]
+struct VideoArgs {
+    /// Constant frame rate, like `23.976` or `24000/1001`.
+    #[arg(long, conflicts_with = "timecodes")]
+    fps: Option<String>,
+    /// Matroska timecodes file (v1 or v2) for variable frame rate video.
+    #[arg(long)]
+    timecodes: Option<PathBuf>,
+}
+
+impl VideoArgs {
+    /// Frame times from arguments, or from `fallback` frame rate (like one stored in keyframes file).
+    fn frame_times(&self, fallback: Option<f64>) -> Result<FrameTimes, String> {
+        if let Some(path) = &self.timecodes {
+            let data = read_input(&Some(path.clone()))?;
+            return FrameTimes::parse_timecodes(&data).map_err(|_| format!("invalid timecodes file {}", path.display()));
+        }
+        let fps = match &self.fps {
+            Some(fps) => {
+                let value = match fps.split_once('/') {
+                    Some((num, den)) => num.parse::<f64>().ok().zip(den.parse::<f64>().ok()).map(|(n, d)| n / d),
+                    None => fps.parse::<f64>().ok(),
+                };
+                value.ok_or_else(|| format!("invalid frame rate {:?}", fps))?
+            }
+            None => fallback.ok_or("frame rate is not known, use --fps or --timecodes")?,
+        };
+        FrameTimes::constant(fps).map_err(|_| format!("invalid frame rate {}", fps))
+    }
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Format {
+    Ass,
+    Srt,
+    /// Visible text of dialogue lines.
+    Txt,
+}
+
+fn read_bytes(path: &Option<PathBuf>) -> Result<Vec<u8>, String> {
+    let mut data = vec![];
+    match path {
+        Some(path) if path != Path::new("-") => {
+            data = std::fs::read(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?
+        }
+        _ => {
+            std::io::stdin().read_to_end(&mut data).map_err(|e| format!("cannot read stdin: {}", e))?;
+        }
+    };
+    Ok(data)
+}
+
+/// Reads input in any encoding, see [`asai::parse_bytes`].
+fn read_input(path: &Option<PathBuf>) -> Result<String, String> {
+    Ok(asai::parse_bytes(&read_bytes(path)?).text)
+}
+
+fn encoding(path: &Option<PathBuf>, json: bool) -> Result<(), String> {
+    let decoded = asai::parse_bytes(&read_bytes(path)?);
+    let source = match decoded.source {
+        EncodingSource::Bom => "bom".to_string(),
+        EncodingSource::Forced => "forced".to_string(),
+        EncodingSource::Utf8 => "utf-8".to_string(),
+        EncodingSource::StyleCharset(charset) => format!("style charset {}", charset),
+        EncodingSource::Guessed => "guessed".to_string(),
+    };
+    if json {
+        print_json(json!({
+            "encoding": decoded.encoding.name(),
+            "source": source,
+            "bom": decoded.bom,
+            "had_errors": decoded.had_errors,
+        }));
+    } else {
+        println!("{} ({}{})", decoded.encoding.name(), source, if decoded.had_errors { ", with errors" } else { "" });
+    }
+    Ok(())
+}
+
+fn is_srt(data: &str) -> bool {
+    let mut lines = data.lines().map(str::trim).filter(|x| !x.is_empty());
+    matches!(
+        (lines.next(), lines.next()),
+        (Some(index), Some(time)) if index.parse::<u64>().is_ok() && time.contains("-->")
+    )
+}
+
+fn load(data: &str) -> Result<Ass<'_>, String> {
+    if is_srt(data) {
+        return Ok(parse_srt(data));
+    }
+    let ass = asai::parse_str(data).map_err(|_| "[Events] section not found".to_string())?;
+    let unparsed = ass
+        .styles
+        .unparsed
+        .iter()
+        .chain(&ass.events.unparsed)
+        .filter(|x| !x.text.starts_with(';'))
+        .count();
+    if unparsed > 0 {
+        eprintln!("warning: {} lines can't be parsed, they are kept unchanged", unparsed);
+    }
+    Ok(ass)
+}
+
+/// Parses offsets like `1.5` (seconds), `250ms`, `-0:01:02.50`.
+fn parse_offset(s: &str) -> Option<i64> {
+    let (sign, s) = match s.strip_prefix('-') {
+        Some(s) => (-1, s),
+        None => (1, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let millis = if let Some(ms) = s.strip_suffix("ms") {
+        ms.parse::<f64>().ok()?
+    } else {
+        s.split(':').try_fold(0.0, |acc, x| Some(acc * 60.0 + x.parse::<f64>().ok()?))? * 1000.0
+    };
+    Some(sign * millis.round() as i64)
+}
+
+fn print_json(value: serde_json::Value) {
+    println!("{}", serde_json::to_string_pretty(&value).unwrap());
+}
+
+fn info(ass: &Ass, json: bool) {
+    let info = &ass.info;
+    let (x, y) = info.resolution.resolved();
+    let dialogues = ass.events.events.iter().filter(|x| x.0 == EventKey::Dialogue).count();
+    if json {
+        print_json(json!({
+            "title": info.title,
+            "script_type": info.version,
+            "resolution": { "x": info.resolution.x, "y": info.resolution.y },
+            "wrap_style": info.wrap_style as u8,
+            "authors": {
+                "script": info.authors.script_authors().collect::<Vec<_>>(),
+                "translation": info.authors.translation_authors().collect::<Vec<_>>(),
+                "editing": info.authors.editing_authors().collect::<Vec<_>>(),
+                "timing": info.authors.timing_authors().collect::<Vec<_>>(),
+                "updated_by": info.authors.updated_by_authors().collect::<Vec<_>>(),
+                "update_details": info.authors.update_details(),
+            },
+            "other": info.other.iter().map(|(k, v)| (k.to_string(), json!(v))).collect::<serde_json::Map<_, _>>(),
+            "styles": ass.styles.styles.len(),
+            "events": ass.events.events.len(),
+            "dialogues": dialogues,
+            "fonts": ass.fonts.iter().map(|x| x.name.as_ref()).collect::<Vec<_>>(),
+        }));
+        return;
+    }
+    println!("Title:       {}", info.title.unwrap_or("-"));
+    println!("Script type: {}", info.version);
+    println!("Resolution:  {}x{}", x, y);
+    println!("Wrap style:  {}", info.wrap_style as u8);
+    let authors = &info.authors;
+    for (name, value) in [
+        ("Script", authors.script()),
+        ("Translation", authors.translation()),
+        ("Editing", authors.editing()),
+        ("Timing", authors.timing()),
+        ("Updated by", authors.updated_by()),
+    ] {
+        if let Some(value) = value {
+            println!("{:<12} {}", format!("{}:", name), value);
+        }
+    }
+    for (key, value) in &info.other {
+        println!("{}: {}", key, value);
+    }
+    println!("Styles:      {}", ass.styles.styles.len());
+    println!("Events:      {} ({} dialogue)", ass.events.events.len(), dialogues);
+    println!("Fonts:       {}", ass.fonts.len());
+}
+
+fn stats(ass: &Ass, json: bool) {
+    let dialogues: Vec<_> = ass
+        .events
+        .events
+        .iter()
+        .filter(|x| x.0 == EventKey::Dialogue)
+        .map(|x| &x.1)
+        .collect();
+    let mut by_style: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut by_actor: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut characters = 0;
+    let mut max_cps: f64 = 0.0;
+    for event in &dialogues {
+        *by_style.entry(&event.style).or_default() += 1;
+        if !event.actor.is_empty() {
+            *by_actor.entry(&event.actor).or_default() += 1;
+        }
+        let count = event.plain_text(WrapStyle::default()).text.chars().filter(|x| !x.is_whitespace()).count();
+        characters += count;
+        let seconds = event.end.saturating_sub(event.start).as_secs_f64();
+        if seconds > 0.0 {
+            max_cps = max_cps.max(count as f64 / seconds);
+        }
+    }
+    let first = dialogues.iter().map(|x| x.start).min().unwrap_or_default();
+    let last = dialogues.iter().map(|x| x.end).max().unwrap_or_default();
+    let total: Duration = dialogues.iter().map(|x| x.end.saturating_sub(x.start)).sum();
+    if json {
+        print_json(json!({
+            "events": ass.events.events.len(),
+            "dialogues": dialogues.len(),
+            "comments": ass.events.events.len() - dialogues.len(),
+            "first_start_ms": first.as_millis() as u64,
+            "last_end_ms": last.as_millis() as u64,
+            "total_duration_ms": total.as_millis() as u64,
+            "characters": characters,
+            "max_cps": max_cps,
+            "by_style": by_style,
+            "by_actor": by_actor,
+        }));
+        return;
+    }
+    println!("Events:         {}", ass.events.events.len());
+    println!("Dialogues:      {}", dialogues.len());
+    println!("Comments:       {}", ass.events.events.len() - dialogues.len());
+    println!("First start:    {}", format_timestamp(first));
+    println!("Last end:       {}", format_timestamp(last));
+    println!("Total duration: {}", format_timestamp(total));
+    println!("Characters:     {}", characters);
+    println!("Max CPS:        {:.1}", max_cps);
+    println!("By style:");
+    for (style, count) in by_style {
+        println!("  {:<20} {}", style, count);
+    }
+    if !by_actor.is_empty() {
+        println!("By actor:");
+        for (actor, count) in by_actor {
+            println!("  {:<20} {}", actor, count);
+        }
+    }
+}
+
+fn extract_fonts(ass: &Ass, output: &Path, json: bool) -> Result<(), String> {
+    std::fs::create_dir_all(output).map_err(|e| format!("cannot create {}: {}", output.display(), e))?;
+    let mut written = vec![];
+    for font in &ass.fonts {
+        // Keep only file name, so fonts can't be written outside of the directory.
+        let name = Path::new(font.name.as_ref())
+            .file_name()
+            .ok_or_else(|| format!("invalid font name {:?}", font.name))?;
+        let path = output.join(name);
+        let data = font.decode();
+        std::fs::write(&path, &data).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+        written.push((path, data.len()));
+    }
+    if json {
+        print_json(json!(written
+            .iter()
+            .map(|(path, size)| json!({ "path": path, "size": size }))
+            .collect::<Vec<_>>()));
+    } else {
+        for (path, size) in written {
+            println!("{} ({} bytes)", path.display(), size);
+        }
+    }
+    Ok(())
+}
+
+fn lint_config(disable: &[String]) -> Result<LintConfig, String> {
+    let mut config = LintConfig::default();
+    for id in disable {
+        let rule = id.parse::<Rule>().map_err(|_| format!("unknown rule {:?}", id))?;
+        config.disable(rule);
+    }
+    Ok(config)
+}
+
+fn lint(ass: &Ass, config: &LintConfig, json: bool) -> bool {
+    let findings = asai::lint::lint(ass, config);
+    let severity = |x: Severity| match x {
+        Severity::Info => "info",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    };
+    let location = |x: Location| match x {
+        Location::Script => "script".to_string(),
+        Location::Style(i) => format!("style {}", i + 1),
+        Location::Event(i) => format!("event {}", i + 1),
+    };
+    if json {
+        print_json(json!(findings
+            .iter()
+            .map(|x| json!({
+                "rule": x.rule.id(),
+                "severity": severity(x.severity),
+                "location": location(x.location),
+                "message": x.message,
+                "fixable": !x.fix.is_empty(),
+            }))
+            .collect::<Vec<_>>()));
+    } else {
+        for x in &findings {
+            println!("{}: {}: {} [{}]", location(x.location), severity(x.severity), x.message, x.rule);
+        }
+    }
+    findings.iter().any(|x| x.severity == Severity::Error)
+}
+
+fn extract_mkv(path: &Path, track: usize, list: bool, json: bool) -> Result<(), String> {
+    let data = std::fs::read(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let mkv = Matroska::read(&data).map_err(|e| format!("cannot read {}: {:?}", path.display(), e))?;
+    if list {
+        if json {
+            print_json(json!(mkv
+                .tracks
+                .iter()
+                .map(|x| json!({
+                    "number": x.number,
+                    "codec": x.codec_id,
+                    "name": x.name,
+                    "language": x.language,
+                    "events": x.blocks.len(),
+                }))
+                .collect::<Vec<_>>()));
+        } else {
+            for (i, x) in mkv.tracks.iter().enumerate() {
+                let name = x.name.as_deref().unwrap_or("-");
+                let language = x.language.as_deref().unwrap_or("und");
+                println!("{}: track {} {} {:?} ({}), {} events", i, x.number, x.codec_id, name, language, x.blocks.len());
+            }
+        }
+        return Ok(());
+    }
+    if mkv.tracks.is_empty() {
+        return Err(format!("no ASS tracks in {}", path.display()));
+    }
+    let ass = mkv.to_ass(track).map_err(|_| format!("cannot read ASS track {}", track))?;
+    write_output(&ass.to_string())
+}
+
+fn write_output(s: &str) -> Result<(), String> {
+    std::io::stdout().write_all(s.as_bytes()).map_err(|e| format!("cannot write output: {}", e))
+}
+
+fn run(cli: Cli) -> Result<ExitCode, String> {
+    match cli.command {
+        Command::Info { input } => info(&load(&read_input(&input)?)?, cli.json),
+        Command::Validate { input } => {
+            let diagnostics = validate::validate(&read_input(&input)?);
+            validate::print(&diagnostics, cli.json);
+            if diagnostics.iter().any(|x| x.severity == validate::Severity::Error) {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Lint { input, disable, max_cps } => {
+            let mut config = lint_config(&disable)?;
+            config.max_cps = max_cps;
+            if lint(&load(&read_input(&input)?)?, &config, cli.json) {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Fix { input, disable } => {
+            let data = read_input(&input)?;
+            let mut ass = load(&data)?;
+            let findings = asai::lint::lint(&ass, &lint_config(&disable)?);
+            ass.events.apply_edits(findings.iter().flat_map(|x| &x.fix));
+            write_output(&ass.to_string())?;
+        }
+        Command::Encoding { input } => encoding(&input, cli.json)?,
+        Command::Optimize { input } => {
+            let data = read_input(&input)?;
+            let mut ass = load(&data)?;
+            ass.events.optimize_tags(&ass.styles);
+            write_output(&ass.to_string())?;
+        }
+        Command::Timing { input, lead_in, lead_out, link, bias, style, keyframes, video } => {
+            let data = read_input(&input)?;
+            let mut ass = load(&data)?;
+            let keyframes = match keyframes {
+                Some(path) => {
+                    let keyframes = Keyframes::parse(&read_input(&Some(path.clone()))?)
+                        .map_err(|_| format!("invalid keyframes file {}", path.display()))?;
+                    keyframes.times(&video.frame_times(keyframes.fps)?)
+                }
+                None => vec![],
+            };
+            let options = TimingOptions {
+                keyframes,
+                styles: style.iter().map(String::as_str).collect(),
+                lead_in: Duration::from_millis(lead_in),
+                lead_out: Duration::from_millis(lead_out),
+                link_threshold: Duration::from_millis(link),
+                link_bias: bias,
+                ..Default::default()
+            };
+            ass.events.process_timing(&options);
+            write_output(&ass.to_string())?;
+        }
+        Command::Snap { input, video } => {
+            let frames = video.frame_times(None)?;
+            let data = read_input(&input)?;
+            let mut ass = load(&data)?;
+            for i in ass.events.snap_to_frames(&frames) {
+                let event = &ass.events.events[i].1;
+                eprintln!("warning: event {} at {} is shown on no frame", i + 1, format_timestamp(event.start));
+            }
+            write_output(&ass.to_string())?;
+        }
+        Command::Shift { offset, input } => {
+            let millis = parse_offset(&offset).ok_or_else(|| format!("invalid offset {:?}", offset))?;
+            let data = read_input(&input)?;
+            let mut ass = load(&data)?;
+            ass.events.shift(millis);
+            write_output(&ass.to_string())?;
+        }
+        Command::Convert { to, input } => {
+            let data = read_input(&input)?;
+            let ass = load(&data)?;
+            let out = match to {
+                Format::Ass => ass.to_string(),
+                Format::Srt => ass.events.to_srt(ass.info.wrap_style),
+                Format::Txt => ass
+                    .events
+                    .events
+                    .iter()
+                    .filter(|x| x.0 == EventKey::Dialogue)
+                    .map(|x| x.1.plain_text(ass.info.wrap_style).text + "\n")
+                    .collect(),
+            };
+            write_output(&out)?;
+        }
+        Command::Fmt { input, preserve_columns } => {
+            let order = if preserve_columns { ColumnOrder::Preserved } else { ColumnOrder::Canonical };
+            let mut out = String::new();
+            load(&read_input(&input)?)?.write(&mut out, order).map_err(|e| e.to_string())?;
+            write_output(&out)?;
+        }
+        Command::ExtractFonts { input, output } => extract_fonts(&load(&read_input(&input)?)?, &output, cli.json)?,
+        Command::ExtractMkv { input, track, list } => extract_mkv(&input, track, list, cli.json)?,
+        Command::Stats { input } => stats(&load(&read_input(&input)?)?, cli.json),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("asai: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
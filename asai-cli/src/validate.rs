@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+use asai::iter::span::SpannedElement;
+use asai::iter::{Element, InvalidElement};
+use asai::structure::event::{Event, EventKey};
+use asai::structure::style::Style;
+use asai::structure::format::{Format, FormatDiagnostic, FormatKind};
+use asai::structure::{ColumnPlan, FromLine, EVENT_FORMAT, STYLE_FORMAT};
+use serde_json::json;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// Line number, starting from 1. Zero for problems of the whole file.
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+fn diagnostic(line: usize, severity: Severity, message: impl Into<String>) -> Diagnostic {
+    Diagnostic { line, severity, message: message.into() }
+}
+
+/// Columns that break parsing of the section are errors, others are warnings.
+fn check_format(diagnostics: &mut Vec<Diagnostic>, line: usize, value: &str, kind: FormatKind) {
+    for problem in Format::parse(value).check(kind) {
+        let severity = match problem {
+            FormatDiagnostic::Missing(_) | FormatDiagnostic::TextNotLast => Severity::Error,
+            // The column is missing as well, lines can't be parsed.
+            FormatDiagnostic::Misspelled { expected, .. } if kind.mandatory().contains(&expected) => Severity::Error,
+            _ => Severity::Warning,
+        };
+        diagnostics.push(diagnostic(line, severity, problem.to_string()));
+    }
+}
+
+pub fn validate(data: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut section: Option<&str> = None;
+    let mut sections = HashSet::new();
+    let mut style_plan = ColumnPlan::<Style>::new(STYLE_FORMAT);
+    let mut event_plan = ColumnPlan::<Event>::new(EVENT_FORMAT);
+    let mut styles = HashSet::new();
+    // Style references are checked after all styles are known.
+    let mut references: Vec<(usize, String)> = vec![];
+    for SpannedElement { element, line: n, .. } in asai::iter::parse_str_spanned(data) {
+        let attachments = matches!(section, Some("Fonts" | "Graphics"));
+        match element {
+            Ok(Element::SectionDefinition(name)) => {
+                if !sections.insert(name) {
+                    diagnostics.push(diagnostic(n, Severity::Warning, format!("duplicate section [{}]", name)));
+                }
+                section = Some(name);
+            }
+            Ok(Element::Comment(_)) => {}
+            Err(_) | Ok(_) if attachments => {}
+            Err(InvalidElement(line)) => {
+                diagnostics.push(diagnostic(n, Severity::Error, format!("cannot parse line {:?}", line)))
+            }
+            Ok(Element::Line { .. }) if section.is_none() => {
+                diagnostics.push(diagnostic(n, Severity::Warning, "line outside of any section"))
+            }
+            Ok(Element::Line { name, value }) => match (section.unwrap(), name) {
+                ("V4+ Styles", "Format") => {
+                    check_format(&mut diagnostics, n, value, FormatKind::Styles);
+                    style_plan = ColumnPlan::new(value);
+                }
+                ("V4+ Styles", "Style") => match Style::from_planned_line(value, &style_plan) {
+                    Ok(style) => {
+                        if !styles.insert(style.name) {
+                            diagnostics.push(diagnostic(n, Severity::Warning, format!("duplicate style {:?}", style.name)));
+                        }
+                    }
+                    Err(_) => diagnostics.push(diagnostic(n, Severity::Error, "invalid style")),
+                },
+                ("V4+ Styles", name) => {
+                    diagnostics.push(diagnostic(n, Severity::Warning, format!("unknown key {:?} in [V4+ Styles]", name)))
+                }
+                ("Events", "Format") => {
+                    check_format(&mut diagnostics, n, value, FormatKind::Events);
+                    event_plan = ColumnPlan::new(value);
+                }
+                ("Events", name) => {
+                    if name.parse::<EventKey>().is_err() {
+                        diagnostics.push(diagnostic(n, Severity::Warning, format!("unsupported event type {:?}", name)));
+                        continue;
+                    }
+                    match Event::from_planned_line(value, &event_plan) {
+                        Ok(event) => {
+                            if event.end < event.start {
+                                diagnostics.push(diagnostic(n, Severity::Error, "event ends before it starts"));
+                            }
+                            references.push((n, event.style.to_string()));
+                        }
+                        Err(_) => diagnostics.push(diagnostic(n, Severity::Error, "invalid event")),
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+    for required in ["Script Info", "Events"] {
+        if !sections.contains(required) {
+            diagnostics.push(diagnostic(0, Severity::Error, format!("missing [{}] section", required)));
+        }
+    }
+    for (n, style) in references {
+        if !styles.contains(style.as_str()) {
+            diagnostics.push(diagnostic(n, Severity::Warning, format!("undefined style {:?}", style)));
+        }
+    }
+    diagnostics.sort_by_key(|x| x.line);
+    diagnostics
+}
+
+pub fn print(diagnostics: &[Diagnostic], json: bool) {
+    if json {
+        let value: Vec<_> = diagnostics
+            .iter()
+            .map(|x| {
+                json!({
+                    "line": x.line,
+                    "severity": match x.severity {
+                        Severity::Error => "error",
+                        Severity::Warning => "warning",
+                    },
+                    "message": x.message,
+                })
+            })
+            .collect();
+        super::print_json(json!(value));
+        return;
+    }
+    for d in diagnostics {
+        let severity = match d.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        println!("{}: {}: {}", d.line, severity, d.message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn severities(format: &str) -> Vec<(Severity, String)> {
+        let mut diagnostics = vec![];
+        check_format(&mut diagnostics, 1, format, FormatKind::Events);
+        diagnostics.into_iter().map(|x| (x.severity, x.message)).collect()
+    }
+
+    #[test]
+    fn misspelled_columns() {
+        let format = "Layer, Strt, End, Style, Nam, MarginL, MarginR, MarginV, Effect, Text";
+        assert_eq!(
+            severities(format),
+            vec![
+                (Severity::Error, "unknown column \"Strt\", did you mean \"Start\"?".to_string()),
+                (Severity::Warning, "unknown column \"Nam\", did you mean \"Name\"?".to_string()),
+            ]
+        );
+    }
+}
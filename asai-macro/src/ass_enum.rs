@@ -0,0 +1,166 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Expr, ExprLit, ExprUnary, Fields, Ident, Lit, UnOp, Variant};
+
+/// Value of a variant in a line.
+#[derive(PartialEq)]
+enum Value {
+    Int(i64),
+    Str(String),
+}
+
+impl Value {
+    fn parse(expr: &Expr) -> syn::Result<Self> {
+        let error = || Error::new_spanned(expr, "expected integer or string literal");
+        match expr {
+            Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) => Ok(Value::Int(int.base10_parse()?)),
+            Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) => Ok(Value::Str(s.value())),
+            Expr::Unary(ExprUnary { op: UnOp::Neg(_), expr, .. }) => match Value::parse(expr) {
+                Ok(Value::Int(int)) => Ok(Value::Int(-int)),
+                _ => Err(error()),
+            },
+            _ => Err(error()),
+        }
+    }
+
+    fn literal(&self) -> Literal {
+        match self {
+            Value::Int(int) => Literal::i64_unsuffixed(*int),
+            Value::Str(s) => Literal::string(s),
+        }
+    }
+}
+
+enum Fallback<'a> {
+    None,
+    /// Unknown values map to this unit variant.
+    Unit(&'a Ident),
+    /// Unknown values are parsed into the field of this variant.
+    Field(&'a Ident),
+}
+
+/// Value of a unit variant: `#[value(...)]` or the name of the variant.
+fn variant_value(variant: &Variant) -> syn::Result<(Value, bool)> {
+    let mut value = None;
+    let mut fallback = false;
+    for attr in &variant.attrs {
+        if attr.path().is_ident("value") {
+            if value.is_some() {
+                return Err(Error::new_spanned(attr, "duplicate attribute"));
+            }
+            value = Some(Value::parse(&attr.parse_args()?)?);
+        } else if attr.path().is_ident("fallback") {
+            attr.meta.require_path_only()?;
+            fallback = true;
+        }
+    }
+    Ok((value.unwrap_or_else(|| Value::Str(variant.ident.to_string())), fallback))
+}
+
+pub fn derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new_spanned(&input.ident, "AssEnum can only be derived for enums"));
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "AssEnum doesn't support generic enums"));
+    }
+    let mut values: Vec<(&Ident, Value)> = vec![];
+    let mut fallback = Fallback::None;
+    for variant in &data.variants {
+        let (value, is_fallback) = variant_value(variant)?;
+        if is_fallback && !matches!(fallback, Fallback::None) {
+            return Err(Error::new_spanned(&variant.ident, "only one variant can be #[fallback]"));
+        }
+        match &variant.fields {
+            Fields::Unit => {
+                if values.iter().any(|x| x.1 == value) {
+                    return Err(Error::new_spanned(&variant.ident, "duplicate value"));
+                }
+                if is_fallback {
+                    fallback = Fallback::Unit(&variant.ident);
+                }
+                values.push((&variant.ident, value));
+            }
+            Fields::Unnamed(fields) if is_fallback && fields.unnamed.len() == 1 => {
+                if variant.attrs.iter().any(|x| x.path().is_ident("value")) {
+                    return Err(Error::new_spanned(&variant.ident, "#[fallback] variant with a field can't have #[value]"));
+                }
+                fallback = Fallback::Field(&variant.ident);
+            }
+            _ => {
+                return Err(Error::new_spanned(
+                    variant,
+                    "AssEnum variants must be unit variants, or a #[fallback] variant with one field",
+                ))
+            }
+        }
+    }
+
+    let type_name = &input.ident;
+    let (strings, ints): (Vec<_>, Vec<_>) = values.iter().partition(|x| matches!(x.1, Value::Str(_)));
+    let string_variants = strings.iter().map(|x| x.0);
+    let string_values = strings.iter().map(|x| x.1.literal());
+    let int_variants = ints.iter().map(|x| x.0);
+    let int_values = ints.iter().map(|x| x.1.literal());
+    let parse_ints = match ints.is_empty() {
+        true => quote! {},
+        false => quote! {
+            if let Ok(i) = s.parse::<i64>() {
+                match i {
+                    #(#int_values => return Ok(Self::#int_variants), )*
+                    _ => {}
+                }
+            }
+        },
+    };
+    let unknown = match fallback {
+        Fallback::None => quote! { Err(asai::structure::InvalidValue) },
+        Fallback::Unit(variant) => quote! { Ok(Self::#variant) },
+        Fallback::Field(variant) => quote! { Ok(Self::#variant(s.parse().map_err(|_| asai::structure::InvalidValue)?)) },
+    };
+    let variants = values.iter().map(|x| x.0);
+    let literals = values.iter().map(|x| x.1.literal());
+    let write_field = match fallback {
+        Fallback::Field(variant) => quote! { Self::#variant(v) => std::fmt::Display::fmt(v, f), },
+        _ => quote! {},
+    };
+
+    Ok(quote! {
+        impl std::str::FromStr for #type_name {
+            type Err = asai::structure::InvalidValue;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let s = s.trim();
+                match s {
+                    #(#string_values => return Ok(Self::#string_variants), )*
+                    _ => {}
+                }
+                #parse_ints
+                #unknown
+            }
+        }
+
+        impl<'a> TryFrom<asai::structure::base_types::LineField<'a>> for #type_name {
+            type Error = asai::structure::InvalidValue;
+
+            fn try_from(value: asai::structure::base_types::LineField<'a>) -> Result<Self, Self::Error> {
+                value.value().parse()
+            }
+        }
+
+        impl std::fmt::Display for #type_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    #(Self::#variants => write!(f, "{}", #literals), )*
+                    #write_field
+                }
+            }
+        }
+
+        impl asai::structure::base_types::ToLineField for #type_name {
+            fn to_line_field(&self) -> String {
+                self.to_string()
+            }
+        }
+    })
+}
@@ -0,0 +1,214 @@
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Error, Expr, Fields, Ident, LitStr, Type};
+
+/// Column naming for fields without `#[name]`, set with `#[rename_all("...")]` on the struct.
+#[derive(Copy, Clone)]
+pub enum RenameAll {
+    None,
+    PascalCase,
+    CamelCase,
+    LowerCase,
+    UpperCase,
+    KebabCase,
+}
+
+impl RenameAll {
+    fn parse(lit: &LitStr) -> syn::Result<Self> {
+        Ok(match lit.value().as_str() {
+            "PascalCase" => Self::PascalCase,
+            "camelCase" => Self::CamelCase,
+            "lowercase" => Self::LowerCase,
+            "UPPERCASE" => Self::UpperCase,
+            "kebab-case" => Self::KebabCase,
+            "snake_case" => Self::None,
+            _ => {
+                return Err(Error::new_spanned(
+                    lit,
+                    "unknown case, expected one of \"PascalCase\", \"camelCase\", \"lowercase\", \"UPPERCASE\", \"snake_case\", \"kebab-case\"",
+                ))
+            }
+        })
+    }
+
+    /// Column name for a snake_case field name.
+    pub fn apply(self, field: &str) -> String {
+        let field = field.strip_prefix("r#").unwrap_or(field);
+        let capitalize = |s: &str| {
+            let mut chars = s.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+        };
+        let words = field.split('_').filter(|x| !x.is_empty());
+        match self {
+            Self::None => field.to_string(),
+            Self::PascalCase => words.map(capitalize).collect(),
+            Self::CamelCase => {
+                let pascal = Self::PascalCase.apply(field);
+                let mut chars = pascal.chars();
+                chars.next().map(|c| c.to_lowercase().chain(chars).collect()).unwrap_or_default()
+            }
+            Self::LowerCase => field.replace('_', ""),
+            Self::UpperCase => field.replace('_', "").to_uppercase(),
+            Self::KebabCase => field.replace('_', "-"),
+        }
+    }
+}
+
+/// How a column value is converted to the field type.
+pub enum Parse {
+    /// `TryFrom<LineField>`, written with `ToLineField`.
+    TryFrom,
+    /// `#[from_str]`: `FromStr`, written with `Display`.
+    FromStr,
+    /// `#[with = "path"]`: `fn(&str) -> Result<T, E>`, written with `ToLineField`.
+    With(Expr),
+}
+
+/// Named field of a derived struct with its column attributes.
+pub struct LineField {
+    pub ident: Ident,
+    pub ty: Type,
+    pub name: LitStr,
+    pub default: Option<Expr>,
+    pub parse: Parse,
+    /// `T` of `Option<T>` fields, which are `None` when the column is absent.
+    pub optional: Option<Type>,
+    /// `#[rest]`: collects columns no other field has.
+    pub rest: bool,
+    /// `#[prefix("...")]`: collects keys starting with it. Only for key-value sections.
+    pub prefix: Option<LitStr>,
+}
+
+impl LineField {
+    /// `#[rest]` and `#[prefix]` fields hold `(key, value)` pairs instead of a single value.
+    pub fn is_collection(&self) -> bool {
+        self.rest || self.prefix.is_some()
+    }
+
+    /// Type of a single column value.
+    pub fn value_type(&self) -> &Type {
+        self.optional.as_ref().unwrap_or(&self.ty)
+    }
+}
+
+/// `T` if the type is written as `Option<T>`.
+fn option_inner(ty: &Type) -> Option<Type> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else { return None };
+    match args.args.first()? {
+        syn::GenericArgument::Type(ty) if args.args.len() == 1 => Some(ty.clone()),
+        _ => None,
+    }
+}
+
+fn rename_all(input: &DeriveInput) -> syn::Result<RenameAll> {
+    let mut rename = RenameAll::None;
+    for attr in &input.attrs {
+        if attr.path().is_ident("rename_all") {
+            rename = RenameAll::parse(&attr.parse_args()?)?;
+        }
+    }
+    Ok(rename)
+}
+
+/// Named fields of a struct, or an error pointing at what can't be derived.
+pub fn named_fields<'a>(input: &'a DeriveInput, derive: &str) -> syn::Result<impl Iterator<Item = &'a syn::Field>> {
+    let message = format!("{} can only be derived for structs with named fields", derive);
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(fields.named.iter()),
+            fields => Err(Error::new(fields.span(), message)),
+        },
+        Data::Enum(data) => Err(Error::new(data.enum_token.span, message)),
+        Data::Union(data) => Err(Error::new(data.union_token.span, message)),
+    }
+}
+
+pub fn line_fields(input: &DeriveInput, derive: &str) -> syn::Result<Vec<LineField>> {
+    let rename = rename_all(input)?;
+    let mut fields: Vec<LineField> = vec![];
+    let mut errors: Option<Error> = None;
+    for field in named_fields(input, derive)? {
+        let field = line_field(field, rename).and_then(|x| {
+            if x.rest && fields.iter().any(|x| x.rest) {
+                return Err(Error::new_spanned(&x.ident, "only one field can be #[rest]"));
+            }
+            Ok(x)
+        });
+        match field {
+            Ok(field) => fields.push(field),
+            Err(e) => match &mut errors {
+                Some(errors) => errors.combine(e),
+                None => errors = Some(e),
+            },
+        }
+    }
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(fields),
+    }
+}
+
+/// Function of `#[with = "path"]` or `#[with(path)]`. Attribute values can only be literals, so the first form
+/// takes a string like serde does.
+fn with_function(attr: &syn::Attribute) -> syn::Result<Expr> {
+    match &attr.meta {
+        syn::Meta::List(_) => attr.parse_args(),
+        meta => match &meta.require_name_value()?.value {
+            Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(path), .. }) => path.parse(),
+            value => Err(Error::new_spanned(value, "expected string with function path")),
+        },
+    }
+}
+
+fn line_field(field: &syn::Field, rename: RenameAll) -> syn::Result<LineField> {
+    let ident = field.ident.clone().unwrap();
+    let mut name = None;
+    let mut default = None;
+    let mut parse = Parse::TryFrom;
+    let mut rest = false;
+    let mut prefix = None;
+    for attr in &field.attrs {
+        let duplicate = || Error::new_spanned(attr, "duplicate attribute");
+        if attr.path().is_ident("name") {
+            if name.is_some() {
+                return Err(duplicate());
+            }
+            name = Some(attr.parse_args::<LitStr>()?);
+        } else if attr.path().is_ident("default") {
+            if default.is_some() {
+                return Err(duplicate());
+            }
+            default = Some(attr.parse_args::<Expr>()?);
+        } else if attr.path().is_ident("from_str") || attr.path().is_ident("with") {
+            if !matches!(parse, Parse::TryFrom) {
+                return Err(Error::new_spanned(attr, "only one of #[from_str] and #[with] can be used"));
+            }
+            parse = match attr.path().is_ident("with") {
+                true => Parse::With(with_function(attr)?),
+                false => {
+                    attr.meta.require_path_only()?;
+                    Parse::FromStr
+                }
+            };
+        } else if attr.path().is_ident("rest") {
+            attr.meta.require_path_only()?;
+            rest = true;
+        } else if attr.path().is_ident("prefix") {
+            if prefix.is_some() {
+                return Err(duplicate());
+            }
+            prefix = Some(attr.parse_args::<LitStr>()?);
+        }
+    }
+    let collection = rest || prefix.is_some();
+    if collection && (name.is_some() || default.is_some() || !matches!(parse, Parse::TryFrom) || rest && prefix.is_some()) {
+        return Err(Error::new_spanned(&ident, "#[rest] and #[prefix] fields can't have other column attributes"));
+    }
+    let name = name.unwrap_or_else(|| LitStr::new(&rename.apply(&ident.to_string()), ident.span()));
+    let optional = option_inner(&field.ty).filter(|_| !collection);
+    Ok(LineField { ident, ty: field.ty.clone(), name, default, parse, optional, rest, prefix })
+}
@@ -1,68 +1,481 @@
 extern crate proc_macro;
+mod ass_enum;
+mod field;
+
 use proc_macro::TokenStream;
-use proc_macro2::Spacing;
-use proc_macro2::TokenTree::Punct;
-use quote::__private::ext::RepToTokensExt;
-use quote::ToTokens;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput, Data, Fields, Meta, Expr};
-use syn::spanned::Spanned;
-
-#[proc_macro_derive(FromLine, attributes(name, default))]
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, DeriveInput, GenericParam, Generics, Ident, Lifetime, LifetimeParam};
+use field::{line_fields, LineField, Parse};
+
+/// Lifetime of the line for `FromLine<'a>`: the first lifetime of the struct, or a new one.
+fn line_lifetime(generics: &Generics) -> (Lifetime, Generics) {
+    let mut impl_generics = generics.clone();
+    match generics.lifetimes().next() {
+        Some(lifetime) => (lifetime.lifetime.clone(), impl_generics),
+        None => {
+            let lifetime = Lifetime::new("'__line", Span::call_site());
+            impl_generics.params.insert(0, GenericParam::Lifetime(LifetimeParam::new(lifetime.clone())));
+            (lifetime, impl_generics)
+        }
+    }
+}
+
+/// Adds `bound` for fields, if the struct has type parameters to bound.
+fn bound_fields(generics: &mut Generics, fields: &[LineField], bound: impl Fn(&LineField) -> Vec<syn::WherePredicate>) {
+    if generics.type_params().next().is_none() {
+        return;
+    }
+    let where_clause = generics.make_where_clause();
+    for field in fields {
+        where_clause.predicates.extend(bound(field));
+    }
+}
+
+/// Bounds needed to read a field.
+fn read_bounds(field: &LineField, lifetime: &Lifetime) -> Vec<syn::WherePredicate> {
+    let ty = field.value_type();
+    let line_field = quote! { asai::structure::base_types::LineField<#lifetime> };
+    match &field.parse {
+        _ if field.is_collection() => vec![parse_quote! { #ty: asai::structure::RestColumns<#lifetime> }],
+        Parse::TryFrom => vec![
+            parse_quote! { #ty: TryFrom<#line_field> },
+            parse_quote! { asai::structure::InvalidValue: From<<#ty as TryFrom<#line_field>>::Error> },
+        ],
+        Parse::FromStr => vec![parse_quote! { #ty: std::str::FromStr }],
+        Parse::With(_) => vec![],
+    }
+}
+
+/// Bounds needed to write a field. Collections need a lifetime for `RestColumns` and are left to the compiler.
+fn write_bounds(field: &LineField) -> Vec<syn::WherePredicate> {
+    let ty = field.value_type();
+    match &field.parse {
+        _ if field.is_collection() => vec![],
+        Parse::FromStr => vec![parse_quote! { #ty: std::fmt::Display }],
+        _ => vec![parse_quote! { #ty: asai::structure::base_types::ToLineField }],
+    }
+}
+
+/// Local variable holding the value of a field while reading.
+/// Prefixed, so fields can be named like the locals of the generated function.
+fn local(field: &LineField) -> Ident {
+    format_ident!("__field_{}", field.ident)
+}
+
+/// Declaration of the local of a field with its initial value.
+fn declare_local(field: &LineField) -> proc_macro2::TokenStream {
+    let local = local(field);
+    if field.is_collection() {
+        let ty = &field.ty;
+        return quote! { let mut #local: #ty = Default::default(); };
+    }
+    let ty = field.value_type();
+    match &field.default {
+        Some(default) => quote! { let mut #local: Option<#ty> = Some(#default); },
+        None => quote! { let mut #local: Option<#ty> = None; },
+    }
+}
+
+/// Expression parsing `v: &str` into the value type of a field.
+fn parse_value(field: &LineField) -> proc_macro2::TokenStream {
+    match &field.parse {
+        Parse::TryFrom => quote! { asai::structure::base_types::LineField::new(v).try_into()? },
+        Parse::FromStr => quote! { v.parse().map_err(|_| asai::structure::InvalidValue)? },
+        Parse::With(path) => quote! { (#path)(v).map_err(|_| asai::structure::InvalidValue)? },
+    }
+}
+
+/// `field: value` of the constructed struct. Fields without value fail, unless they are optional.
+fn field_value(field: &LineField) -> proc_macro2::TokenStream {
+    let (ident, local) = (&field.ident, local(field));
+    match field.optional.is_some() || field.is_collection() {
+        true => quote! { #ident: #local },
+        false => quote! { #ident: #local.ok_or(asai::structure::InvalidValue)? },
+    }
+}
+
+/// Function writing a value of a field as string.
+fn write_function(field: &LineField) -> proc_macro2::TokenStream {
+    match field.parse {
+        Parse::FromStr => quote! { ToString::to_string },
+        _ => quote! { asai::structure::base_types::ToLineField::to_line_field },
+    }
+}
+
+/// `#[prefix]` is only meaningful for key-value sections.
+fn reject_prefix(fields: &[LineField], derive: &str) -> syn::Result<()> {
+    match fields.iter().find_map(|x| x.prefix.as_ref()) {
+        Some(prefix) => Err(syn::Error::new_spanned(prefix, format!("#[prefix] is not supported by {}", derive))),
+        None => Ok(()),
+    }
+}
+
+#[proc_macro_derive(FromLine, attributes(name, default, rename_all, rest, from_str, with))]
 pub fn derive_from_line(_item: TokenStream) -> TokenStream {
     let input = parse_macro_input!(_item as DeriveInput);
-    let mut names: Vec<proc_macro2::TokenStream> = vec![];
-    let mut field_names: Vec<proc_macro2::TokenStream> = vec![];
-    let mut field_types: Vec<proc_macro2::TokenStream> = vec![];
-    let mut defaults: Vec<proc_macro2::TokenStream> = vec![];
-
-    if let Data::Struct(d) = input.data {
-        let type_name: proc_macro2::TokenStream = input.ident.into_token_stream().into();
-        if let Fields::Named(fields) = d.fields {
-            for field in fields.named {
-                let mut name: Option<_> = None;
-                let mut default_expr = (quote! {Default::default()}).into_token_stream();
-                let mut default_set = false;
-
-                for attr in field.attrs {
-                    if attr.path().get_ident().unwrap().to_string() == "name" {
-                        let expr = &attr.meta.require_list().unwrap().tokens.next().unwrap();
-                        name = Some(expr.into_token_stream())
-                    } else if attr.path().get_ident().unwrap().to_string() == "default" {
-                        let expr = &attr.meta.require_list().unwrap().tokens.next().unwrap();
-                        default_expr = expr.into_token_stream();
-                        default_expr = (quote! {Some(#default_expr)}).into_token_stream();
-                        default_set = true;
+    from_line(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn from_line(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = line_fields(input, "FromLine")?;
+    reject_prefix(&fields, "FromLine")?;
+    let type_name = &input.ident;
+    let (lifetime, mut generics) = line_lifetime(&input.generics);
+    bound_fields(&mut generics, &fields, |field| read_bounds(field, &lifetime));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let declarations = fields.iter().map(declare_local);
+    let values = fields.iter().map(field_value);
+    let columns: Vec<_> = fields.iter().filter(|x| !x.rest).collect();
+    let names = columns.iter().map(|x| &x.name);
+    let indices = (0..columns.len()).map(proc_macro2::Literal::usize_unsuffixed);
+    let locals = columns.iter().map(|x| local(x));
+    let parsers = columns.iter().map(|x| parse_value(x));
+    let rest: Vec<_> = fields.iter().filter(|x| x.rest).map(local).collect();
+    // Names of columns are only needed for `#[rest]`, other columns are matched by index.
+    let plan_columns = match rest.is_empty() {
+        true => quote! { plan.fields().iter().copied() },
+        false => quote! { plan.columns() },
+    };
+    let column = match rest.is_empty() {
+        true => quote! { field },
+        false => quote! { (k, field) },
+    };
+
+    Ok(quote! {
+        impl #impl_generics asai::structure::FromLine<#lifetime> for #type_name #ty_generics #where_clause {
+            fn from_line(line: &#lifetime str, format: &#lifetime str) -> Result<Self, asai::structure::InvalidValue> {
+                let plan = asai::structure::ColumnPlan::new(format);
+                Self::from_planned_line(line, &plan)
+            }
+
+            fn column_names() -> &'static [&'static str] {
+                &[#(#names, )*]
+            }
+
+            fn from_planned_line(
+                line: &#lifetime str,
+                plan: &asai::structure::ColumnPlan<#lifetime, Self>,
+            ) -> Result<Self, asai::structure::InvalidValue> {
+                #(#declarations)*
+
+                let line_fields = line.splitn(plan.len(), ',');
+                for (#column, v) in #plan_columns.zip(line_fields) {
+                    match field {
+                        #(Some(#indices) => #locals = Some(#parsers), )*
+
+                        #(_ => Extend::extend(&mut #rest, std::iter::once((k, v))),)*
+                        #[allow(unreachable_patterns)]
+                        _ => ()
                     }
                 }
-                let ty = field.ty.into_token_stream();
-                names.push(name.unwrap());
-                field_names.push(field.ident.unwrap().into_token_stream());
-                field_types.push(quote!{ Option<#ty> }.into_token_stream());
-                defaults.push(default_expr)
+                Ok(Self {
+                    #(#values, )*
+                })
             }
         }
-        (quote! {
-            impl<'a> asai::structure::FromLine<'a> for #type_name<'a> {
-                fn from_line(line: &'a str, format: &str) -> Result<Self, asai::structure::InvalidValue> {
-                    #(let mut #field_names: #field_types = #defaults;)*
-
-                    let format_fields: Vec<_>  = format.split(',').map(str::trim).collect();
-                    let line_fields  = line.splitn(format_fields.len(), ',');
-                    for (k, v) in format_fields.into_iter().zip(line_fields) {
-                        match k {
-                            #(#names => #field_names = Some(asai::structure::base_types::LineField::new(v).try_into()?), )*
-
-                            _ => ()
-                        }
+    })
+}
+
+#[proc_macro_derive(ToLine, attributes(name, default, rename_all, rest, from_str, with))]
+pub fn derive_to_line(_item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(_item as DeriveInput);
+    to_line(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn to_line(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = line_fields(input, "ToLine")?;
+    reject_prefix(&fields, "ToLine")?;
+    let type_name = &input.ident;
+    let mut generics = input.generics.clone();
+    bound_fields(&mut generics, &fields, write_bounds);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let (rest, columns): (Vec<_>, Vec<_>) = fields.iter().partition(|x| x.rest);
+    let names: Vec<_> = columns.iter().map(|x| &x.name).collect();
+    let values: Vec<_> = columns
+        .iter()
+        .map(|x| {
+            let write = write_function(x);
+            let ident = &x.ident;
+            match x.optional {
+                Some(_) => quote! {
+                    match &self.#ident {
+                        Some(v) => #write(v),
+                        None => asai::structure::column_default(k).to_string(),
                     }
-                    Ok(Self {
-                        #(#field_names: #field_names.ok_or(asai::structure::InvalidValue)?, )*
+                },
+                None => quote! { #write(&self.#ident) },
+            }
+        })
+        .collect();
+    let fallback = match rest.first() {
+        Some(rest) => {
+            let ident = &rest.ident;
+            quote! {
+                asai::structure::RestColumns::column(&self.#ident, k)
+                    .unwrap_or_else(|| asai::structure::column_default(k))
+                    .to_string()
+            }
+        }
+        None => quote! { asai::structure::column_default(k).to_string() },
+    };
+
+    Ok(quote! {
+        impl #impl_generics asai::structure::ToLine for #type_name #ty_generics #where_clause {
+            fn to_line(&self, format: &str) -> String {
+                format
+                    .split(',')
+                    .map(str::trim)
+                    .map(|k| match k {
+                        #(#names => #values, )*
+
+                        k => #fallback
                     })
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(FromLines, attributes(name, default, rename_all, rest, prefix, from_str, with))]
+pub fn derive_from_lines(_item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(_item as DeriveInput);
+    from_lines(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn from_lines(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = line_fields(input, "FromLines")?;
+    let type_name = &input.ident;
+    let (lifetime, mut generics) = line_lifetime(&input.generics);
+    bound_fields(&mut generics, &fields, |field| read_bounds(field, &lifetime));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let declarations = fields.iter().map(declare_local);
+    let values = fields.iter().map(field_value);
+    let keys: Vec<_> = fields.iter().filter(|x| !x.is_collection()).collect();
+    let names = keys.iter().map(|x| &x.name);
+    let locals = keys.iter().map(|x| local(x));
+    let parsers = keys.iter().map(|x| parse_value(x));
+    let prefixed: Vec<_> = fields.iter().filter(|x| x.prefix.is_some()).collect();
+    let prefixes = prefixed.iter().map(|x| &x.prefix);
+    let prefix_locals = prefixed.iter().map(|x| local(x));
+    let rest = fields.iter().filter(|x| x.rest).map(local);
+
+    Ok(quote! {
+        impl #impl_generics asai::structure::FromLines<#lifetime> for #type_name #ty_generics #where_clause {
+            type Err = asai::structure::InvalidValue;
+
+            fn from_lines(lines: &[(&#lifetime str, &#lifetime str)]) -> Result<Self, Self::Err> {
+                #(#declarations)*
+
+                for &(k, v) in lines {
+                    match k {
+                        #(#names => #locals = Some(#parsers), )*
+                        #(k if k.starts_with(#prefixes) => Extend::extend(&mut #prefix_locals, std::iter::once((k, v))), )*
+
+                        #(_ => Extend::extend(&mut #rest, std::iter::once((k, v))),)*
+                        #[allow(unreachable_patterns)]
+                        _ => ()
+                    }
+                }
+                Ok(Self {
+                    #(#values, )*
+                })
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(ToLines, attributes(name, default, rename_all, rest, prefix, from_str, with))]
+pub fn derive_to_lines(_item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(_item as DeriveInput);
+    to_lines(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn to_lines(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = line_fields(input, "ToLines")?;
+    let type_name = &input.ident;
+    let mut generics = input.generics.clone();
+    bound_fields(&mut generics, &fields, write_bounds);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    // Lines are written in field order. Missing optional values are skipped.
+    let writes = fields.iter().map(|x| {
+        let (ident, name, write) = (&x.ident, &x.name, write_function(x));
+        if x.is_collection() {
+            quote! {
+                for (k, v) in asai::structure::RestColumns::columns(&self.#ident) {
+                    lines.push((k.to_string(), v.to_string()));
+                }
+            }
+        } else if x.optional.is_some() {
+            quote! {
+                if let Some(v) = &self.#ident {
+                    lines.push((#name.to_string(), #write(v)));
                 }
             }
-        }).into()
-    } else {
-        panic!()
+        } else {
+            quote! { lines.push((#name.to_string(), #write(&self.#ident))); }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics asai::structure::ToLines for #type_name #ty_generics #where_clause {
+            fn to_lines(&self) -> Vec<(String, String)> {
+                let mut lines = vec![];
+                #(#writes)*
+                lines
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(AssEnum, attributes(value, fallback))]
+pub fn derive_ass_enum(_item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(_item as DeriveInput);
+    ass_enum::derive(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(result: syn::Result<proc_macro2::TokenStream>) -> String {
+        result.unwrap_err().to_string()
+    }
+
+    #[test]
+    fn not_a_struct() {
+        let input = parse_quote! { enum A { B } };
+        assert_eq!(error(from_line(&input)), "FromLine can only be derived for structs with named fields");
+        let input = parse_quote! { struct A(u32); };
+        assert_eq!(error(to_line(&input)), "ToLine can only be derived for structs with named fields");
+    }
+
+    #[test]
+    fn attributes() {
+        let input = parse_quote! {
+            struct A {
+                #[name("A")]
+                #[name("B")]
+                a: u32,
+            }
+        };
+        assert_eq!(error(from_line(&input)), "duplicate attribute");
+        let input = parse_quote! {
+            #[rename_all("Title Case")]
+            struct A { a: u32 }
+        };
+        assert!(error(from_line(&input)).starts_with("unknown case"));
+        let input = parse_quote! {
+            struct A {
+                #[name(1)]
+                a: u32,
+            }
+        };
+        assert_eq!(error(from_line(&input)), "expected string literal");
+    }
+
+    #[test]
+    fn column_attributes() {
+        let input = parse_quote! {
+            struct A<'a> {
+                #[rest]
+                a: Vec<(&'a str, &'a str)>,
+                #[rest]
+                b: Vec<(&'a str, &'a str)>,
+            }
+        };
+        assert_eq!(error(from_line(&input)), "only one field can be #[rest]");
+        let input = parse_quote! {
+            struct A {
+                #[from_str]
+                #[with = "f"]
+                a: u32,
+            }
+        };
+        assert_eq!(error(from_line(&input)), "only one of #[from_str] and #[with] can be used");
+        let input = parse_quote! {
+            struct A {
+                #[with = 1]
+                a: u32,
+            }
+        };
+        assert_eq!(error(from_line(&input)), "expected string with function path");
+        let input = parse_quote! {
+            struct A<'a> {
+                #[rest]
+                #[name("A")]
+                a: Vec<(&'a str, &'a str)>,
+            }
+        };
+        assert_eq!(error(to_line(&input)), "#[rest] and #[prefix] fields can't have other column attributes");
+    }
+
+    #[test]
+    fn prefix() {
+        let input = parse_quote! {
+            struct A<'a> {
+                #[prefix("Video ")]
+                a: Vec<(&'a str, &'a str)>,
+            }
+        };
+        assert!(from_lines(&input).is_ok());
+        assert!(to_lines(&input).is_ok());
+        assert_eq!(error(from_line(&input)), "#[prefix] is not supported by FromLine");
+        assert_eq!(error(to_line(&input)), "#[prefix] is not supported by ToLine");
+        let input = parse_quote! {
+            struct A<'a> {
+                #[prefix("A")]
+                #[prefix("B")]
+                a: Vec<(&'a str, &'a str)>,
+            }
+        };
+        assert_eq!(error(from_lines(&input)), "duplicate attribute");
+        let input = parse_quote! { struct A; };
+        assert_eq!(error(to_lines(&input)), "ToLines can only be derived for structs with named fields");
+    }
+
+    #[test]
+    fn ass_enum() {
+        let derive = |input| error(ass_enum::derive(&input));
+        assert_eq!(derive(parse_quote! { struct A; }), "AssEnum can only be derived for enums");
+        assert_eq!(derive(parse_quote! { enum A<T> { B(T) } }), "AssEnum doesn't support generic enums");
+        assert_eq!(derive(parse_quote! { enum A { #[value(1)] B, #[value(1)] C } }), "duplicate value");
+        assert_eq!(derive(parse_quote! { enum A { B, #[value("B")] C } }), "duplicate value");
+        assert_eq!(derive(parse_quote! { enum A { #[value(1)] #[value(2)] B } }), "duplicate attribute");
+        assert_eq!(derive(parse_quote! { enum A { #[value(1.5)] B } }), "expected integer or string literal");
+        assert_eq!(derive(parse_quote! { enum A { #[fallback] B, #[fallback] C } }), "only one variant can be #[fallback]");
+        assert_eq!(
+            derive(parse_quote! { enum A { #[fallback] #[value(1)] B(String) } }),
+            "#[fallback] variant with a field can't have #[value]"
+        );
+        assert_eq!(
+            derive(parse_quote! { enum A { B(String) } }),
+            "AssEnum variants must be unit variants, or a #[fallback] variant with one field"
+        );
+    }
+
+    #[test]
+    fn errors_are_combined() {
+        let input = parse_quote! {
+            struct A {
+                #[default(1)]
+                #[default(2)]
+                a: u32,
+                #[name("B")]
+                #[name("B")]
+                b: u32,
+            }
+        };
+        let errors: Vec<_> = from_line(&input).unwrap_err().into_iter().map(|x| x.to_string()).collect();
+        assert_eq!(errors, vec!["duplicate attribute", "duplicate attribute"]);
     }
 }
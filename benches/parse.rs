@@ -0,0 +1,48 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use asai::iter::stream::EventReader;
+use asai::structure::event::Event;
+use asai::structure::{ColumnPlan, FromLine, EVENT_FORMAT};
+
+/// Script with karaoke-like events, as generated by automation scripts.
+fn script(events: usize) -> String {
+    let mut s = String::from("[Script Info]\nScriptType: v4.00+\n\n[Events]\n");
+    s.push_str("Format: ");
+    s.push_str(EVENT_FORMAT);
+    s.push('\n');
+    for i in 0..events {
+        let (start, end) = (i % 360000, i % 360000 + 50);
+        s.push_str(&format!(
+            "Dialogue: 0,0:{:02}:{:02}.{:02},0:{:02}:{:02}.{:02},Default,,0,0,0,fx,{{\\k10\\pos(640,{})}}syl{}\n",
+            start / 6000 % 60, start / 100 % 60, start % 100, end / 6000 % 60, end / 100 % 60, end % 100, i % 720, i
+        ));
+    }
+    s
+}
+
+fn parse(c: &mut Criterion) {
+    let data = script(100_000);
+    let lines: Vec<&str> = data.lines().skip(5).map(|x| &x["Dialogue: ".len()..]).collect();
+
+    c.bench_function("from_line 100k events", |b| {
+        b.iter(|| lines.iter().map(|x| Event::from_line(x, EVENT_FORMAT).unwrap()).collect::<Vec<_>>())
+    });
+    c.bench_function("from_planned_line 100k events", |b| {
+        b.iter(|| {
+            let plan = ColumnPlan::new(EVENT_FORMAT);
+            lines.iter().map(|x| Event::from_planned_line(x, &plan).unwrap()).collect::<Vec<_>>()
+        })
+    });
+    c.bench_function("parse_str 100k events", |b| b.iter(|| asai::parse_str(black_box(&data)).unwrap().events.events.len()));
+    // Columns other than the standard ones, like scripts written by older tools.
+    let custom = data.replacen(EVENT_FORMAT, &EVENT_FORMAT.replace("Name", "Actor"), 1);
+    c.bench_function("EventReader 100k events, custom format", |b| {
+        b.iter(|| EventReader::new(black_box(custom.as_bytes())).unwrap().count())
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = parse
+}
+criterion_main!(benches);
@@ -7,11 +7,9 @@ fn main() {
     let mut args = args();
     let data = std::fs::read_to_string(args.nth(1).expect("No path provided.")).unwrap();
     let mut lines_count: u64 = 0;
-    for el in parse_str(&data) {
-        if let Ok(el) = el {
-            if el.is_line() {
-                lines_count += 1
-            }
+    for el in parse_str(&data).flatten() {
+        if el.is_line() {
+            lines_count += 1
         }
     }
     println!("{lines_count}")
@@ -5,6 +5,7 @@ use asai::structure::formatted_section::FormattedSection;
 use asai_macro::FromLine;
 
 #[derive(FromLine, Debug)]
+#[allow(dead_code)]
 struct MyEvent<'a> {
     #[name("Text")]
     text: &'a str,
@@ -0,0 +1,222 @@
+//! Decoding scripts that are not UTF-8: UTF-16 and legacy Windows codepages.
+
+use chardetng::EncodingDetector;
+use crate::structure::{Ass, InvalidValue, SectionParseError};
+
+pub use encoding_rs::Encoding;
+
+/// How the encoding of a script was chosen.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum EncodingSource {
+    /// Byte order mark at the start of the data.
+    Bom,
+    /// Set in [`DecodeOptions::encoding`].
+    Forced,
+    /// Data is valid UTF-8.
+    Utf8,
+    /// `Encoding` field of a style, with the charset number.
+    StyleCharset(u32),
+    /// Guessed from the content.
+    Guessed,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DecodeOptions {
+    /// Encoding to use when there is no BOM.
+    pub encoding: Option<&'static Encoding>,
+    /// Guess legacy encodings for data that is not UTF-8. Without it such data is decoded as UTF-8 with replacement characters.
+    pub guess: bool,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self { encoding: None, guess: true }
+    }
+}
+
+/// Decoded script text with its original encoding.
+#[derive(Debug, Clone)]
+pub struct Decoded {
+    pub text: String,
+    pub encoding: &'static Encoding,
+    pub source: EncodingSource,
+    /// Data started with a byte order mark.
+    pub bom: bool,
+    /// Some bytes were invalid and replaced with U+FFFD.
+    pub had_errors: bool,
+}
+
+impl Decoded {
+    /// Parses the text, see [`crate::parse_str`].
+    pub fn ass(&self) -> Result<Ass<'_>, SectionParseError> {
+        crate::parse_str(&self.text)
+    }
+
+    /// Encodes text back in the original encoding, with BOM if the original had one.
+    /// Fails if text has characters the encoding can't represent.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, InvalidValue> {
+        let mut out = vec![];
+        if self.encoding == encoding_rs::UTF_16LE || self.encoding == encoding_rs::UTF_16BE {
+            let le = self.encoding == encoding_rs::UTF_16LE;
+            let units = self.bom.then_some(0xfeff).into_iter().chain(text.encode_utf16());
+            for unit in units {
+                out.extend(if le { unit.to_le_bytes() } else { unit.to_be_bytes() });
+            }
+            return Ok(out);
+        }
+        if self.bom && self.encoding == encoding_rs::UTF_8 {
+            out.extend("\u{feff}".as_bytes());
+        }
+        let (bytes, _, unmappable) = self.encoding.encode(text);
+        if unmappable {
+            return Err(InvalidValue);
+        }
+        out.extend_from_slice(&bytes);
+        Ok(out)
+    }
+}
+
+/// Encoding for a charset number from the `Encoding` field of a style, as in Windows `LOGFONT`.
+/// `0` (ANSI) and `1` (default) depend on the system of the author and give `None`.
+pub fn charset_encoding(charset: u32) -> Option<&'static Encoding> {
+    Some(match charset {
+        128 => encoding_rs::SHIFT_JIS,
+        129 => encoding_rs::EUC_KR,
+        134 => encoding_rs::GBK,
+        136 => encoding_rs::BIG5,
+        161 => encoding_rs::WINDOWS_1253,
+        162 => encoding_rs::WINDOWS_1254,
+        163 => encoding_rs::WINDOWS_1258,
+        177 => encoding_rs::WINDOWS_1255,
+        178 => encoding_rs::WINDOWS_1256,
+        186 => encoding_rs::WINDOWS_1257,
+        204 => encoding_rs::WINDOWS_1251,
+        222 => encoding_rs::WINDOWS_874,
+        238 => encoding_rs::WINDOWS_1250,
+        _ => return None,
+    })
+}
+
+/// First charset of `Style` lines that maps to an encoding. Lines are read as bytes,
+/// style lines of legacy encodings are ASCII up to the font name.
+fn style_charset(data: &[u8]) -> Option<(u32, &'static Encoding)> {
+    data.split(|x| *x == b'\n')
+        .filter(|line| line.starts_with(b"Style:"))
+        .filter_map(|line| {
+            let field = line.rsplit(|x| *x == b',').next()?;
+            let charset: u32 = std::str::from_utf8(field).ok()?.trim().parse().ok()?;
+            Some((charset, charset_encoding(charset)?))
+        })
+        .next()
+}
+
+/// UTF-16 without BOM, recognized by zero bytes around the leading ASCII character.
+fn utf16_without_bom(data: &[u8]) -> Option<&'static Encoding> {
+    match data {
+        [a, 0, b, 0, ..] if a.is_ascii() && b.is_ascii() => Some(encoding_rs::UTF_16LE),
+        [0, a, 0, b, ..] if a.is_ascii() && b.is_ascii() => Some(encoding_rs::UTF_16BE),
+        _ => None,
+    }
+}
+
+fn guess(data: &[u8]) -> (&'static Encoding, EncodingSource) {
+    if let Some((charset, encoding)) = style_charset(data) {
+        return (encoding, EncodingSource::StyleCharset(charset));
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(data, true);
+    (detector.guess(None, false), EncodingSource::Guessed)
+}
+
+pub fn decode(data: &[u8], options: &DecodeOptions) -> Decoded {
+    let (encoding, source, bom, data) = match Encoding::for_bom(data) {
+        Some((encoding, len)) => (encoding, EncodingSource::Bom, true, &data[len..]),
+        None => {
+            // Zero bytes are valid UTF-8, so UTF-16 is checked first.
+            let utf16 = utf16_without_bom(data).filter(|_| options.guess);
+            let (encoding, source) = match (options.encoding, utf16) {
+                (Some(encoding), _) => (encoding, EncodingSource::Forced),
+                (None, Some(encoding)) => (encoding, EncodingSource::Guessed),
+                (None, None) if std::str::from_utf8(data).is_ok() => (encoding_rs::UTF_8, EncodingSource::Utf8),
+                (None, None) if options.guess => guess(data),
+                (None, None) => (encoding_rs::UTF_8, EncodingSource::Utf8),
+            };
+            (encoding, source, false, data)
+        }
+    };
+    let (text, had_errors) = encoding.decode_without_bom_handling(data);
+    Decoded { text: text.into_owned(), encoding, source, bom, had_errors }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "[V4+ Styles]\nStyle: Default,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,204\n\n[Events]\nDialogue: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,Привет, мир\n";
+
+    fn utf16(s: &str, le: bool) -> Vec<u8> {
+        s.encode_utf16().flat_map(|x| if le { x.to_le_bytes() } else { x.to_be_bytes() }).collect()
+    }
+
+    #[test]
+    fn utf8() {
+        let decoded = crate::parse_bytes(SCRIPT.as_bytes());
+        assert_eq!((decoded.encoding, decoded.source, decoded.bom), (encoding_rs::UTF_8, EncodingSource::Utf8, false));
+        assert_eq!(decoded.ass().unwrap().events.events[0].1.text, "Привет, мир");
+        let with_bom = [&[0xEF, 0xBB, 0xBF], SCRIPT.as_bytes()].concat();
+        let decoded = crate::parse_bytes(&with_bom);
+        assert_eq!((decoded.source, decoded.bom), (EncodingSource::Bom, true));
+        assert_eq!(decoded.text, SCRIPT);
+        assert_eq!(decoded.encode(&decoded.text).unwrap(), with_bom);
+    }
+
+    #[test]
+    fn utf16_bom() {
+        let data = [vec![0xFF, 0xFE], utf16(SCRIPT, true)].concat();
+        let decoded = crate::parse_bytes(&data);
+        assert_eq!((decoded.encoding, decoded.source), (encoding_rs::UTF_16LE, EncodingSource::Bom));
+        assert_eq!(decoded.text, SCRIPT);
+        assert_eq!(decoded.encode(SCRIPT).unwrap(), data);
+    }
+
+    #[test]
+    fn utf16_without_bom() {
+        let data = utf16(SCRIPT, false);
+        let decoded = crate::parse_bytes(&data);
+        assert_eq!((decoded.encoding, decoded.source, decoded.bom), (encoding_rs::UTF_16BE, EncodingSource::Guessed, false));
+        assert_eq!(decoded.encode(SCRIPT).unwrap(), data);
+    }
+
+    #[test]
+    fn style_charset() {
+        let (data, _, _) = encoding_rs::WINDOWS_1251.encode(SCRIPT);
+        let decoded = crate::parse_bytes(&data);
+        assert_eq!((decoded.encoding, decoded.source), (encoding_rs::WINDOWS_1251, EncodingSource::StyleCharset(204)));
+        assert_eq!(decoded.text, SCRIPT);
+        assert!(!decoded.had_errors);
+        assert!(decoded.encode("日本").is_err());
+    }
+
+    #[test]
+    fn guessed() {
+        let script = SCRIPT.replace(",204\n", ",1\n");
+        let (data, _, _) = encoding_rs::WINDOWS_1251.encode(&script);
+        let decoded = crate::parse_bytes(&data);
+        assert_eq!((decoded.encoding, decoded.source), (encoding_rs::WINDOWS_1251, EncodingSource::Guessed));
+    }
+
+    #[test]
+    fn options() {
+        let (data, _, _) = encoding_rs::WINDOWS_1251.encode(SCRIPT);
+        let forced = decode(&data, &DecodeOptions { encoding: Some(encoding_rs::KOI8_R), guess: true });
+        assert_eq!((forced.encoding, forced.source), (encoding_rs::KOI8_R, EncodingSource::Forced));
+        let lossy = decode(&data, &DecodeOptions { encoding: None, guess: false });
+        assert_eq!(lossy.encoding, encoding_rs::UTF_8);
+        assert!(lossy.had_errors);
+    }
+
+    #[test]
+    fn without_events() {
+        assert_eq!(crate::parse_bytes(b"[Script Info]\n").ass().err(), Some(SectionParseError::SectionNotFound));
+    }
+}
@@ -1,3 +1,6 @@
+pub mod span;
+pub mod stream;
+
 use std::str::Lines;
 
 #[derive(Debug, PartialEq, Eq)]
@@ -9,24 +12,15 @@ pub enum Element<'a> {
 
 impl<'a> Element<'a> {
     pub fn is_line(&self) -> bool {
-        match self {
-            Element::Line {..} => true,
-            _ => false
-        }
+        matches!(self, Element::Line { .. })
     }
 
     pub fn is_section(&self) -> bool {
-        match self {
-            Element::SectionDefinition(_) => true,
-            _ => false
-        }
+        matches!(self, Element::SectionDefinition(_))
     }
 
     pub fn is_comment(&self) -> bool {
-        match self {
-            Element::Comment(_) => true,
-            _ => false
-        }
+        matches!(self, Element::Comment(_))
     }
 }
 
@@ -37,10 +31,15 @@ pub struct Elements<'a, T: Iterator<Item = &'a str>> {
     iter: T,
 }
 
-pub fn parse_str(s: &str) -> Elements<Lines> {
+pub fn parse_str(s: &str) -> Elements<'_, Lines<'_>> {
     Elements::new(s.lines())
 }
 
+/// Elements of `s` with line numbers and byte ranges.
+pub fn parse_str_spanned(s: &str) -> span::SpannedElements<'_> {
+    span::SpannedElements::new(s)
+}
+
 impl<'a, T: Iterator<Item = &'a str>> Elements<'a, T> {
     pub fn new(iter: T) -> Self {
         Self { iter }
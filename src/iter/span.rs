@@ -0,0 +1,129 @@
+use std::ops::Range;
+use crate::iter::{Element, Elements, InvalidElement};
+
+/// Element with its position in the source text. Ranges are byte offsets into the source.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SpannedElement<'a> {
+    pub element: Result<Element<'a>, InvalidElement<'a>>,
+    /// Line number, starting from 1. Blank lines are counted.
+    pub line: usize,
+    /// Whole line, without line ending.
+    pub span: Range<usize>,
+    /// Section name or line name. Empty range at the start of the line for comments and invalid lines.
+    pub name: Range<usize>,
+    /// Line value, comment text or the whole invalid line. Empty range at the end of the line for sections.
+    pub value: Range<usize>,
+}
+
+/// Like [`Elements`], but keeps positions. Handles `\n` and `\r\n` line endings and a leading BOM.
+pub struct SpannedElements<'a> {
+    source: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> SpannedElements<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let pos = if source.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+        Self { source, pos, line: 0 }
+    }
+
+    fn range(&self, s: &str) -> Range<usize> {
+        let start = s.as_ptr() as usize - self.source.as_ptr() as usize;
+        start..start + s.len()
+    }
+}
+
+impl<'a> Iterator for SpannedElements<'a> {
+    type Item = SpannedElement<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.source.len() {
+            let rest = &self.source[self.pos..];
+            let (line, len) = match rest.find('\n') {
+                Some(end) => (&rest[..end], end + 1),
+                None => (rest, rest.len()),
+            };
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            self.pos += len;
+            self.line += 1;
+            let Some(element) = Elements::new(std::iter::once(line)).next() else { continue };
+            let span = self.range(line);
+            let (name, value) = match element {
+                Ok(Element::SectionDefinition(name)) => (self.range(name), span.end..span.end),
+                Ok(Element::Line { name, value }) => (self.range(name), self.range(value)),
+                Ok(Element::Comment(text)) => (span.start..span.start, self.range(text)),
+                Err(InvalidElement(line)) => (span.start..span.start, self.range(line)),
+            };
+            return Some(SpannedElement { element, line: self.line, span, name, value });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = "\u{feff}[Script Info]\r\nTitle:  My Title\r\n\r\n; comment\r\n\r\n[Events]\nDialogue: 0,a\r\nbad line";
+
+    #[test]
+    fn positions() {
+        let elements: Vec<_> = SpannedElements::new(DATA).collect();
+        let lines: Vec<_> = elements.iter().map(|x| (x.line, &DATA[x.span.clone()])).collect();
+        assert_eq!(
+            lines,
+            vec![
+                (1, "[Script Info]"),
+                (2, "Title:  My Title"),
+                (4, "; comment"),
+                (6, "[Events]"),
+                (7, "Dialogue: 0,a"),
+                (8, "bad line"),
+            ]
+        );
+        // The BOM is not a part of the first line.
+        assert_eq!(elements[0].span, 3..16);
+        let parts: Vec<_> = elements.iter().map(|x| (&DATA[x.name.clone()], &DATA[x.value.clone()])).collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("Script Info", ""),
+                // Only one space after `:` is a part of the separator, like in `Elements`.
+                ("Title", " My Title"),
+                ("", " comment"),
+                ("Events", ""),
+                ("Dialogue", "0,a"),
+                ("", "bad line"),
+            ]
+        );
+        assert_eq!(elements[0].value, 16..16);
+        assert_eq!(elements[2].name, elements[2].span.start..elements[2].span.start);
+    }
+
+    #[test]
+    fn ranges_match_elements() {
+        for x in SpannedElements::new(DATA) {
+            match x.element {
+                Ok(Element::Line { name, value }) => {
+                    assert_eq!((&DATA[x.name.clone()], &DATA[x.value.clone()]), (name, value));
+                    // Ranges point into the source, not just to equal text.
+                    assert_eq!(name.as_ptr(), DATA[x.name].as_ptr());
+                    assert_eq!(value.as_ptr(), DATA[x.value].as_ptr());
+                }
+                Ok(Element::SectionDefinition(name)) => assert_eq!(&DATA[x.name], name),
+                Ok(Element::Comment(text)) | Err(InvalidElement(text)) => assert_eq!(&DATA[x.value], text),
+            }
+        }
+    }
+
+    #[test]
+    fn line_endings() {
+        let lf: Vec<_> = SpannedElements::new("a: 1\n\nb: 2\n").map(|x| (x.line, x.span)).collect();
+        assert_eq!(lf, vec![(1, 0..4), (3, 6..10)]);
+        let crlf: Vec<_> = SpannedElements::new("a: 1\r\n\r\nb: 2").map(|x| (x.line, x.span)).collect();
+        assert_eq!(crlf, vec![(1, 0..4), (3, 8..12)]);
+        assert_eq!(SpannedElements::new("\u{feff}").count(), 0);
+        assert_eq!(SpannedElements::new("\r\n\n").count(), 0);
+    }
+}
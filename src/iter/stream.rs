@@ -0,0 +1,271 @@
+use std::fmt::{Display, Formatter};
+use std::io::BufRead;
+use crate::iter::{Element, Elements, InvalidElement};
+use crate::structure::event::{Event, EventKey};
+use crate::structure::{Ass, ColumnPlan, FromLine, EVENT_FORMAT};
+
+/// Owned version of [`Element`], produced by [`ElementReader`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OwnedElement {
+    SectionDefinition(String),
+    Line { name: String, value: String },
+    Comment(String),
+}
+
+impl OwnedElement {
+    pub fn as_element(&self) -> Element<'_> {
+        match self {
+            OwnedElement::SectionDefinition(name) => Element::SectionDefinition(name),
+            OwnedElement::Line { name, value } => Element::Line { name, value },
+            OwnedElement::Comment(comment) => Element::Comment(comment),
+        }
+    }
+}
+
+impl From<Element<'_>> for OwnedElement {
+    fn from(element: Element<'_>) -> Self {
+        match element {
+            Element::SectionDefinition(name) => OwnedElement::SectionDefinition(name.to_string()),
+            Element::Line { name, value } => OwnedElement::Line { name: name.to_string(), value: value.to_string() },
+            Element::Comment(comment) => OwnedElement::Comment(comment.to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum StreamError {
+    Io(std::io::Error),
+    /// Line that is not a section, comment or `key: value` line.
+    InvalidElement(String),
+    /// Value of an event line that can't be parsed with the current `Format`.
+    InvalidEvent(String),
+}
+
+impl From<std::io::Error> for StreamError {
+    fn from(e: std::io::Error) -> Self {
+        StreamError::Io(e)
+    }
+}
+
+impl Display for StreamError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamError::Io(e) => write!(f, "{}", e),
+            StreamError::InvalidElement(line) => write!(f, "invalid line {:?}", line),
+            StreamError::InvalidEvent(value) => write!(f, "invalid event {:?}", value),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+/// Reads elements line by line from any [`BufRead`], keeping only the current line in memory.
+pub struct ElementReader<R> {
+    reader: R,
+    buf: String,
+    first: bool,
+    /// Last line is returned again by the next read.
+    pending: bool,
+}
+
+impl<R: BufRead> ElementReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, buf: String::new(), first: true, pending: false }
+    }
+
+    /// Makes the next read return the last element again.
+    pub fn unread(&mut self) {
+        self.pending = true;
+    }
+
+    /// Raw text of the last line read, without line ending.
+    pub fn line(&self) -> &str {
+        &self.buf
+    }
+
+    /// Reads the next element, borrowing it from the internal buffer. Blank lines are skipped.
+    pub fn read_element(&mut self) -> std::io::Result<Option<Result<Element<'_>, InvalidElement<'_>>>> {
+        while !std::mem::take(&mut self.pending) {
+            self.buf.clear();
+            if self.reader.read_line(&mut self.buf)? == 0 {
+                return Ok(None);
+            }
+            let len = self.buf.trim_end_matches(['\n', '\r']).len();
+            self.buf.truncate(len);
+            if std::mem::take(&mut self.first) && self.buf.starts_with('\u{feff}') {
+                self.buf.drain(..'\u{feff}'.len_utf8());
+            }
+            if !self.buf.is_empty() {
+                break;
+            }
+        }
+        Ok(Elements::new(std::iter::once(self.buf.as_str())).next())
+    }
+
+    /// Underlying reader. A line given back with [`ElementReader::unread`] is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: BufRead> Iterator for ElementReader<R> {
+    type Item = Result<OwnedElement, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_element() {
+            Ok(Some(Ok(element))) => Some(Ok(element.into())),
+            Ok(Some(Err(InvalidElement(line)))) => Some(Err(StreamError::InvalidElement(line.to_string()))),
+            Ok(None) => None,
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+
+/// Reads events of a script one at a time. Everything before `[Events]` is kept as [`EventReader::header`],
+/// events themselves are not collected, so memory use doesn't depend on the number of events.
+pub struct EventReader<R> {
+    elements: ElementReader<R>,
+    header: String,
+    format: String,
+    /// Fields of [`ColumnPlan`] for `format`, which the reader can't keep borrowed by the plan itself.
+    fields: Vec<Option<usize>>,
+    done: bool,
+}
+
+impl<R: BufRead> EventReader<R> {
+    /// Reads the script up to `[Events]` section.
+    pub fn new(reader: R) -> Result<Self, StreamError> {
+        let mut elements = ElementReader::new(reader);
+        let mut header = String::new();
+        while let Some(element) = elements.read_element()? {
+            let is_events = element == Ok(Element::SectionDefinition("Events"));
+            header.push_str(elements.line());
+            header.push('\n');
+            if is_events {
+                break;
+            }
+        }
+        let fields = ColumnPlan::<Event>::new(EVENT_FORMAT).fields().to_vec();
+        Ok(Self { elements, header, format: EVENT_FORMAT.to_string(), fields, done: false })
+    }
+
+    /// Text of the script up to and including `[Events]` line.
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    /// Parses [`EventReader::header`]. Returned script has no events.
+    pub fn header_ass(&self) -> Ass<'_> {
+        let ass = if self.header.lines().any(|x| x == "[Events]") {
+            crate::parse_str(&self.header)
+        } else {
+            Ass::from_elements(Elements::new(self.header.lines().chain(std::iter::once("[Events]"))))
+        };
+        ass.expect("header always has [Events]")
+    }
+
+    /// Underlying reader, positioned after the last event read. Sections after `[Events]` can be read from it,
+    /// starting with the header of the next section if the iterator already reached it.
+    pub fn into_inner(self) -> ElementReader<R> {
+        self.elements
+    }
+}
+
+impl<R: BufRead> Iterator for EventReader<R> {
+    type Item = Result<(EventKey, Event<'static>), StreamError>;
+
+    /// Yields `Dialogue` and `Comment` events until the end of `[Events]` section.
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let element = match self.elements.read_element() {
+                Ok(Some(element)) => element,
+                Ok(None) => break,
+                Err(e) => return Some(Err(e.into())),
+            };
+            match element {
+                Ok(Element::SectionDefinition(_)) => {
+                    self.elements.unread();
+                    self.done = true;
+                }
+                Ok(Element::Line { name: "Format", value }) => {
+                    self.fields = ColumnPlan::<Event>::new(value).fields().to_vec();
+                    self.format = value.to_string();
+                }
+                Ok(Element::Line { name, value }) => {
+                    let Ok(key) = name.parse::<EventKey>() else { continue };
+                    let plan = ColumnPlan::borrowed(&self.format, &self.fields);
+                    let event = Event::from_planned_line(value, &plan);
+                    return Some(match event {
+                        Ok(event) => Ok((key, event.into_owned())),
+                        Err(_) => Err(StreamError::InvalidEvent(value.to_string())),
+                    });
+                }
+                Ok(Element::Comment(_)) => {}
+                Err(InvalidElement(line)) => return Some(Err(StreamError::InvalidElement(line.to_string()))),
+            }
+        }
+        self.done = true;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "\u{feff}[Script Info]\r\nTitle: Test\r\n\r\n[Events]\n\
+        Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+        Dialogue: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,a\n\
+        ; comment\n\
+        Picture: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,x.png\n\
+        Comment: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,b\n\
+        Dialogue: 0,0:00:01,0:00:02.00,Default,,0,0,0,,c\n\
+        \n[Aegisub Project Garbage]\nAudio File: a.wav\n";
+
+    #[test]
+    fn elements() {
+        let mut reader = ElementReader::new(SCRIPT.as_bytes());
+        assert_eq!(reader.next().unwrap().unwrap(), OwnedElement::SectionDefinition("Script Info".to_string()));
+        assert_eq!(reader.line(), "[Script Info]");
+        reader.unread();
+        assert_eq!(reader.read_element().unwrap(), Some(Ok(Element::SectionDefinition("Script Info"))));
+        let title = OwnedElement::Line { name: "Title".to_string(), value: "Test".to_string() };
+        assert_eq!(reader.next().unwrap().unwrap(), title);
+        assert_eq!(reader.count(), 9);
+    }
+
+    #[test]
+    fn events() {
+        let mut reader = EventReader::new(SCRIPT.as_bytes()).unwrap();
+        assert_eq!(reader.header(), "[Script Info]\nTitle: Test\n[Events]\n");
+        assert_eq!(reader.header_ass().info.title, Some("Test"));
+        let (key, event) = reader.next().unwrap().unwrap();
+        assert_eq!((key, event.text.as_ref()), (EventKey::Dialogue, "a"));
+        let (key, event) = reader.next().unwrap().unwrap();
+        assert_eq!((key, event.text.as_ref()), (EventKey::Comment, "b"));
+        assert!(matches!(reader.next(), Some(Err(StreamError::InvalidEvent(_)))));
+        assert!(reader.next().is_none());
+        assert!(reader.next().is_none());
+        let mut rest = reader.into_inner();
+        assert_eq!(rest.read_element().unwrap(), Some(Ok(Element::SectionDefinition("Aegisub Project Garbage"))));
+        assert_eq!(rest.read_element().unwrap(), Some(Ok(Element::Line { name: "Audio File", value: "a.wav" })));
+        assert_eq!(rest.read_element().unwrap(), None);
+    }
+
+    #[test]
+    fn header_without_events() {
+        let reader = EventReader::new("[Script Info]\nTitle: Test\n".as_bytes()).unwrap();
+        assert_eq!(reader.header_ass().info.title, Some("Test"));
+        assert_eq!(reader.count(), 0);
+    }
+
+    #[test]
+    fn events_with_custom_format() {
+        let script = "[Events]\nFormat: Start, End, Layer, Text\nDialogue: 0:00:01.00,0:00:02.00,0,a, b\n\
+            Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+            Dialogue: 2,0:00:00.00,0:00:01.00,Sign,,0,0,0,,c\n";
+        let events: Vec<_> = EventReader::new(script.as_bytes()).unwrap().map(|x| x.unwrap().1).collect();
+        assert_eq!((events[0].start, events[0].text.as_ref()), (std::time::Duration::from_secs(1), "a, b"));
+        assert_eq!((events[1].layer, events[1].style.as_ref()), (2, "Sign"));
+    }
+}
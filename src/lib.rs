@@ -1,8 +1,16 @@
-use crate::structure::FromLine;
-
+pub mod encoding;
 pub mod iter;
+pub mod lint;
+pub mod matroska;
 pub mod structure;
+pub mod video;
 
-pub fn parse_str(s: &str) -> structure::Ass {
+pub fn parse_str(s: &str) -> Result<structure::Ass<'_>, structure::SectionParseError> {
     structure::Ass::from_elements(iter::parse_str(s))
-}
\ No newline at end of file
+}
+
+/// Decodes script in any encoding, detecting BOM and guessing legacy codepages.
+/// Use [`encoding::Decoded::ass`] to parse the result.
+pub fn parse_bytes(data: &[u8]) -> encoding::Decoded {
+    encoding::decode(data, &encoding::DecodeOptions::default())
+}
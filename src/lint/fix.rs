@@ -0,0 +1,134 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use std::time::Duration;
+use crate::structure::Events;
+
+/// Change to [`Events`] that fixes a lint finding. Event indices and text ranges
+/// refer to the document the findings were produced for.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Edit {
+    /// Replaces byte range of event text.
+    ReplaceText { event: usize, range: Range<usize>, text: String },
+    SetEnd { event: usize, end: Duration },
+    SetStyle { event: usize, style: String },
+    RemoveEvent { event: usize },
+    /// Stable sort of events by start time.
+    SortEvents,
+}
+
+impl Events<'_> {
+    /// Applies edits produced by [`crate::lint::lint`] for this document, so any subset of
+    /// findings can be fixed at once. Text edits overlapping an earlier edit of the same event are skipped.
+    pub fn apply_edits<'e>(&mut self, edits: impl IntoIterator<Item = &'e Edit>) {
+        let mut replacements: Vec<(usize, &Range<usize>, &str)> = vec![];
+        let mut removed = vec![];
+        let mut sort = false;
+        for edit in edits {
+            match edit {
+                Edit::ReplaceText { event, range, text } => replacements.push((*event, range, text)),
+                Edit::SetEnd { event, end } => {
+                    if let Some((_, e)) = self.events.get_mut(*event) {
+                        e.end = *end;
+                    }
+                }
+                Edit::SetStyle { event, style } => {
+                    if let Some((_, e)) = self.events.get_mut(*event) {
+                        e.style = Cow::Owned(style.clone());
+                    }
+                }
+                Edit::RemoveEvent { event } => removed.push(*event),
+                Edit::SortEvents => sort = true,
+            }
+        }
+
+        // Replace from the end of text, so earlier ranges stay valid.
+        replacements.sort_by_key(|(event, range, _)| (*event, std::cmp::Reverse(range.start)));
+        let mut last: Option<(usize, usize)> = None;
+        for (event, range, text) in replacements {
+            let Some((_, e)) = self.events.get_mut(event) else { continue };
+            let overlaps = matches!(last, Some((le, start)) if le == event && range.end > start);
+            let valid = range.start <= range.end && e.text.get(range.clone()).is_some();
+            if overlaps || !valid {
+                continue;
+            }
+            e.text.to_mut().replace_range(range.clone(), text);
+            last = Some((event, range.start));
+        }
+
+        removed.sort_unstable();
+        removed.dedup();
+        for event in removed.into_iter().rev() {
+            if event < self.events.len() {
+                self.events.remove(event);
+            }
+        }
+        if sort {
+            self.events.sort_by_key(|(_, e)| e.start);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(lines: &[(&str, &str)]) -> Events<'static> {
+        let mut data = "[Events]\n".to_string();
+        for (start, text) in lines {
+            data.push_str(&format!("Dialogue: 0,0:00:{},0:00:09.00,Default,,0,0,0,,{}\n", start, text));
+        }
+        let ass = crate::parse_str(&data).unwrap();
+        let events = ass.events.events.into_iter().map(|(k, e)| (k, e.into_owned())).collect();
+        Events { events, ..Default::default() }
+    }
+
+    fn texts(events: &Events) -> Vec<String> {
+        events.events.iter().map(|(_, e)| e.text.to_string()).collect()
+    }
+
+    #[test]
+    fn overlapping_text_edits() {
+        let mut events = events(&[("00.00", "abcdefg"), ("01.00", "xyz")]);
+        let replace = |event, range, text: &str| Edit::ReplaceText { event, range, text: text.to_string() };
+        events.apply_edits(&[
+            replace(0, 0..3, "1"),
+            replace(0, 2..5, "2"),
+            replace(0, 6..7, "3"),
+            replace(1, 0..1, "4"),
+            // Out of text and reversed ranges are skipped.
+            replace(1, 2..9, "5"),
+            replace(1, Range { start: 2, end: 1 }, "6"),
+        ]);
+        // Edits are applied from the end of text, so `0..3` overlaps `2..5` applied before it.
+        assert_eq!(texts(&events), vec!["ab2f3", "4yz"]);
+    }
+
+    #[test]
+    fn remove_by_original_index() {
+        let mut events = events(&[("00.00", "a"), ("01.00", "b"), ("02.00", "c"), ("03.00", "d"), ("04.00", "e")]);
+        events.apply_edits(&[
+            Edit::RemoveEvent { event: 1 },
+            Edit::RemoveEvent { event: 3 },
+            Edit::RemoveEvent { event: 1 },
+            Edit::RemoveEvent { event: 9 },
+            // Indices of other edits are not shifted by removals either.
+            Edit::SetStyle { event: 4, style: "Sign".to_string() },
+            Edit::ReplaceText { event: 2, range: 0..1, text: "C".to_string() },
+        ]);
+        assert_eq!(texts(&events), vec!["a", "C", "e"]);
+        assert_eq!(events.events[2].1.style, "Sign");
+    }
+
+    #[test]
+    fn sort_after_edits() {
+        let mut events = events(&[("03.00", "a"), ("01.00", "b"), ("02.00", "c"), ("01.00", "d")]);
+        events.apply_edits(&[
+            Edit::SortEvents,
+            Edit::RemoveEvent { event: 2 },
+            Edit::SetEnd { event: 0, end: Duration::from_secs(4) },
+        ]);
+        // Stable sort, done after removal.
+        assert_eq!(texts(&events), vec!["b", "d", "a"]);
+        assert_eq!(events.events[2].1.end, Duration::from_secs(4));
+    }
+}
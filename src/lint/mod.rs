@@ -0,0 +1,634 @@
+mod fix;
+
+pub use fix::Edit;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::str::FromStr;
+use crate::structure::base_types::Color;
+use crate::structure::event::{format_timestamp, EventKey};
+use crate::structure::tags::{self, parse_number, Block, OverrideItem, Tag};
+use crate::structure::{Ass, InvalidValue};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Rule {
+    UndefinedStyle,
+    Overlap,
+    ZeroDuration,
+    EndBeforeStart,
+    UnbalancedBraces,
+    UnknownTag,
+    MalformedTag,
+    DuplicateStyle,
+    MissingResolution,
+    FontNotEmbedded,
+    CharactersPerSecond,
+    AdjacentOverrides,
+    DuplicateEvent,
+    UnsortedEvents,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 14] = [
+        Rule::UndefinedStyle,
+        Rule::Overlap,
+        Rule::ZeroDuration,
+        Rule::EndBeforeStart,
+        Rule::UnbalancedBraces,
+        Rule::UnknownTag,
+        Rule::MalformedTag,
+        Rule::DuplicateStyle,
+        Rule::MissingResolution,
+        Rule::FontNotEmbedded,
+        Rule::CharactersPerSecond,
+        Rule::AdjacentOverrides,
+        Rule::DuplicateEvent,
+        Rule::UnsortedEvents,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Rule::UndefinedStyle => "undefined-style",
+            Rule::Overlap => "overlap",
+            Rule::ZeroDuration => "zero-duration",
+            Rule::EndBeforeStart => "end-before-start",
+            Rule::UnbalancedBraces => "unbalanced-braces",
+            Rule::UnknownTag => "unknown-tag",
+            Rule::MalformedTag => "malformed-tag",
+            Rule::DuplicateStyle => "duplicate-style",
+            Rule::MissingResolution => "missing-resolution",
+            Rule::FontNotEmbedded => "font-not-embedded",
+            Rule::CharactersPerSecond => "cps",
+            Rule::AdjacentOverrides => "adjacent-overrides",
+            Rule::DuplicateEvent => "duplicate-event",
+            Rule::UnsortedEvents => "unsorted-events",
+        }
+    }
+
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            Rule::EndBeforeStart | Rule::UnbalancedBraces | Rule::MalformedTag => Severity::Error,
+            Rule::AdjacentOverrides | Rule::DuplicateEvent | Rule::UnsortedEvents => Severity::Info,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = InvalidValue;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rule::ALL.into_iter().find(|x| x.id() == s).ok_or(InvalidValue)
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// What part of the script a finding refers to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Location {
+    Script,
+    /// Index in [`crate::structure::Styles::styles`].
+    Style(usize),
+    /// Index in [`crate::structure::Events::events`].
+    Event(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub rule: Rule,
+    pub severity: Severity,
+    pub location: Location,
+    pub message: String,
+    /// Edits that fix the problem, empty if it can't be fixed automatically.
+    pub fix: Vec<Edit>,
+}
+
+/// Which rules to run and how to report them. All rules are enabled by default.
+#[derive(Debug, Clone)]
+pub struct LintConfig {
+    disabled: HashSet<Rule>,
+    severities: HashMap<Rule, Severity>,
+    /// Threshold for [`Rule::CharactersPerSecond`]. Whitespace is not counted.
+    pub max_cps: f64,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            disabled: HashSet::new(),
+            severities: HashMap::new(),
+            max_cps: 25.0,
+        }
+    }
+}
+
+impl LintConfig {
+    pub fn enable(&mut self, rule: Rule) -> &mut Self {
+        self.disabled.remove(&rule);
+        self
+    }
+
+    pub fn disable(&mut self, rule: Rule) -> &mut Self {
+        self.disabled.insert(rule);
+        self
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+
+    pub fn set_severity(&mut self, rule: Rule, severity: Severity) -> &mut Self {
+        self.severities.insert(rule, severity);
+        self
+    }
+
+    pub fn severity(&self, rule: Rule) -> Severity {
+        self.severities.get(&rule).copied().unwrap_or(rule.default_severity())
+    }
+}
+
+struct Linter<'c> {
+    config: &'c LintConfig,
+    findings: Vec<Finding>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: Rule, location: Location, message: impl Into<String>) {
+        self.report_fix(rule, location, message, vec![]);
+    }
+
+    fn report_fix(&mut self, rule: Rule, location: Location, message: impl Into<String>, fix: Vec<Edit>) {
+        if self.config.is_enabled(rule) {
+            self.findings.push(Finding {
+                rule,
+                severity: self.config.severity(rule),
+                location,
+                message: message.into(),
+                fix,
+            });
+        }
+    }
+}
+
+/// Checks script with rules enabled in `config`. Findings are ordered by location.
+pub fn lint(ass: &Ass, config: &LintConfig) -> Vec<Finding> {
+    let mut linter = Linter { config, findings: vec![] };
+    let linter = &mut linter;
+    if ass.info.resolution.x.is_none() || ass.info.resolution.y.is_none() {
+        linter.report(Rule::MissingResolution, Location::Script, "PlayResX or PlayResY is not set");
+    }
+
+    let mut names = HashSet::new();
+    let mut fonts: Vec<String> = vec![];
+    for (i, style) in ass.styles.styles.iter().enumerate() {
+        if !names.insert(style.name) {
+            linter.report(Rule::DuplicateStyle, Location::Style(i), format!("duplicate style {:?}", style.name));
+        }
+        fonts.push(style.font_name.to_string());
+    }
+
+    for (i, (key, event)) in ass.events.events.iter().enumerate() {
+        let location = Location::Event(i);
+        if *key != EventKey::Dialogue {
+            continue;
+        }
+        if ass.styles.get(&event.style).is_none() {
+            // Renderers use `Default` for unknown styles, so it is the fix only if it exists.
+            let fix = match ass.styles.get("Default") {
+                Some(_) if event.style != "Default" => vec![Edit::SetStyle { event: i, style: "Default".to_string() }],
+                _ => vec![],
+            };
+            let message = format!("style {:?} is not defined", event.style);
+            linter.report_fix(Rule::UndefinedStyle, location, message, fix);
+        }
+        if event.end < event.start {
+            let fix = vec![Edit::SetEnd { event: i, end: event.start }];
+            linter.report_fix(Rule::EndBeforeStart, location, "event ends before it starts", fix);
+        } else if event.end == event.start {
+            linter.report(Rule::ZeroDuration, location, "event has zero duration");
+        }
+        if !check_braces(linter, i, &event.text) {
+            check_adjacent_overrides(linter, i, &event.text);
+        }
+        for block in tags::parse(&event.text) {
+            if let Block::Override(items) = block {
+                for item in &items {
+                    if let OverrideItem::Tag(tag) = item {
+                        check_tag(linter, location, tag, &mut fonts);
+                    }
+                }
+            }
+        }
+        let seconds = event.end.saturating_sub(event.start).as_secs_f64();
+        if seconds > 0.0 {
+            let wrap_style = ass.info.wrap_style;
+            let count = event.plain_text(wrap_style).text.chars().filter(|x| !x.is_whitespace()).count();
+            let cps = count as f64 / seconds;
+            if cps > config.max_cps {
+                linter.report(
+                    Rule::CharactersPerSecond,
+                    location,
+                    format!("{:.1} characters per second, maximum is {}", cps, config.max_cps),
+                );
+            }
+        }
+    }
+
+    check_overlaps(linter, ass);
+    check_duplicates(linter, ass);
+    let events = &ass.events.events;
+    if events.windows(2).any(|x| x[1].1.start < x[0].1.start) {
+        let fix = vec![Edit::SortEvents];
+        linter.report_fix(Rule::UnsortedEvents, Location::Script, "events are not sorted by start time", fix);
+    }
+    check_fonts(linter, ass, fonts);
+
+    let order = |x: &Finding| match x.location {
+        Location::Script => (0, 0),
+        Location::Style(i) => (1, i),
+        Location::Event(i) => (2, i),
+    };
+    linter.findings.sort_by_key(order);
+    std::mem::take(&mut linter.findings)
+}
+
+/// Reports unbalanced braces with edits closing unclosed blocks and removing stray `}`.
+/// Returns whether there were any.
+fn check_braces(linter: &mut Linter, event: usize, text: &str) -> bool {
+    let mut open = false;
+    let mut message = None;
+    let mut fix = vec![];
+    let mut insert = |message: &mut Option<&str>, range: Range<usize>, text: &str, problem| {
+        message.get_or_insert(problem);
+        fix.push(Edit::ReplaceText { event, range, text: text.to_string() });
+    };
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' if open => insert(&mut message, i..i, "}", "`{` inside of override block"),
+            '{' => open = true,
+            '}' if !open => insert(&mut message, i..i + 1, "", "`}` without matching `{`"),
+            '}' => open = false,
+            _ => {}
+        }
+    }
+    if open {
+        insert(&mut message, text.len()..text.len(), "}", "`{` is never closed");
+    }
+    match message {
+        Some(message) => {
+            linter.report_fix(Rule::UnbalancedBraces, Location::Event(event), message, fix);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Reports `}{` between override blocks that can be merged into one.
+fn check_adjacent_overrides(linter: &mut Linter, event: usize, text: &str) {
+    let mut fix = vec![];
+    let mut block_start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => block_start = i,
+            '}' if text[i + 1..].starts_with("{\\") => {
+                // Merging would move following tags into an unclosed `\t(` or `\clip(`.
+                let block = &text[block_start..i];
+                if block.matches('(').count() <= block.matches(')').count() {
+                    fix.push(Edit::ReplaceText { event, range: i..i + 2, text: String::new() });
+                }
+            }
+            _ => {}
+        }
+    }
+    if !fix.is_empty() {
+        linter.report_fix(Rule::AdjacentOverrides, Location::Event(event), "adjacent override blocks can be merged", fix);
+    }
+}
+
+fn is_number(s: &str) -> bool {
+    parse_number(s).is_some()
+}
+
+/// Checks arguments of a known tag. Returns description of the problem.
+fn tag_problem(tag: &Tag) -> Option<String> {
+    let args: Vec<&str> = tag.args.iter().map(|x| x.as_ref()).collect();
+    let numbers = |counts: &[usize]| {
+        if !tag.parens || !counts.contains(&args.len()) || !args.iter().all(|x| is_number(x)) {
+            let counts: Vec<String> = counts.iter().map(|x| x.to_string()).collect();
+            Some(format!("\\{} expects {} numbers in parentheses", tag.name, counts.join(" or ")))
+        } else {
+            None
+        }
+    };
+    match tag.name {
+        "pos" | "org" => numbers(&[2]),
+        "move" => numbers(&[4, 6]),
+        "fad" => numbers(&[2]),
+        "fade" => numbers(&[7]),
+        "clip" | "iclip" => match args[..] {
+            [_, _, _, _] => numbers(&[4]),
+            [drawing] if !drawing.is_empty() => None,
+            [scale, drawing] if is_number(scale) && !drawing.is_empty() => None,
+            _ => Some(format!("\\{} expects a rectangle or a drawing", tag.name)),
+        },
+        "t" => {
+            let (last, times) = args.split_last()?;
+            if !last.starts_with('\\') || times.len() > 3 || !times.iter().all(|x| is_number(x)) {
+                Some("\\t expects optional times and acceleration followed by tags".to_string())
+            } else {
+                None
+            }
+        }
+        "c" | "1c" | "2c" | "3c" | "4c" => match args[..] {
+            [] => None,
+            [color] if color.parse::<Color>().is_ok() => None,
+            _ => Some(format!("invalid color in \\{}", tag.name)),
+        },
+        "alpha" | "1a" | "2a" | "3a" | "4a" => match args[..] {
+            [] => None,
+            [alpha] => {
+                let hex = alpha.trim_start_matches('&').trim_start_matches(['H', 'h']).trim_end_matches('&');
+                match u8::from_str_radix(hex, 16) {
+                    Ok(_) => None,
+                    Err(_) => Some(format!("invalid alpha in \\{}", tag.name)),
+                }
+            }
+            _ => Some(format!("invalid alpha in \\{}", tag.name)),
+        },
+        "fn" | "r" => None,
+        "an" => match tag.number(0) {
+            Some(x) if (1.0..=9.0).contains(&x) => None,
+            _ => Some("\\an expects value from 1 to 9".to_string()),
+        },
+        "q" => match tag.number(0) {
+            Some(x) if (0.0..=3.0).contains(&x) => None,
+            _ => Some("\\q expects value from 0 to 3".to_string()),
+        },
+        _ if tag.parens => Some(format!("\\{} does not take arguments in parentheses", tag.name)),
+        // Numeric tags. Empty value resets tag to the style value.
+        _ => match args[..] {
+            [] => None,
+            [value] if is_number(value) => None,
+            _ => Some(format!("\\{} expects a number", tag.name)),
+        },
+    }
+}
+
+fn check_tag(linter: &mut Linter, location: Location, tag: &Tag, fonts: &mut Vec<String>) {
+    if !tag.is_known() {
+        linter.report(Rule::UnknownTag, location, format!("unknown tag \\{}", tag.name));
+        return;
+    }
+    if let Some(problem) = tag_problem(tag) {
+        linter.report(Rule::MalformedTag, location, problem);
+    }
+    if tag.name == "fn" {
+        if let Some(name) = tag.arg(0).filter(|x| !x.is_empty()) {
+            fonts.push(name.to_string());
+        }
+    }
+    for nested in tag.nested() {
+        if let OverrideItem::Tag(nested) = nested {
+            check_tag(linter, location, &nested, fonts);
+        }
+    }
+}
+
+fn check_duplicates(linter: &mut Linter, ass: &Ass) {
+    let events = &ass.events.events;
+    // First index of every distinct event.
+    let mut first_of: HashMap<_, usize> = HashMap::with_capacity(events.len());
+    for (i, event) in events.iter().enumerate() {
+        let first = *first_of.entry(event).or_insert(i);
+        if first != i {
+            let fix = vec![Edit::RemoveEvent { event: i }];
+            let message = format!("duplicate of event at {}", format_timestamp(events[first].1.start));
+            linter.report_fix(Rule::DuplicateEvent, Location::Event(i), message, fix);
+        }
+    }
+}
+
+fn check_overlaps(linter: &mut Linter, ass: &Ass) {
+    let mut groups: HashMap<(&str, u32), Vec<usize>> = HashMap::new();
+    for (i, (key, event)) in ass.events.events.iter().enumerate() {
+        if *key == EventKey::Dialogue && event.end > event.start {
+            groups.entry((&event.style, event.layer)).or_default().push(i);
+        }
+    }
+    let events = &ass.events.events;
+    for indices in groups.values_mut() {
+        indices.sort_by_key(|i| events[*i].1.start);
+        let mut last: Option<usize> = None;
+        for &i in indices.iter() {
+            if let Some(prev) = last {
+                if events[i].1.start < events[prev].1.end {
+                    linter.report(
+                        Rule::Overlap,
+                        Location::Event(i),
+                        format!(
+                            "overlaps with event at {} of the same style and layer",
+                            format_timestamp(events[prev].1.start),
+                        ),
+                    );
+                }
+            }
+            // Keep the event ending last, so overlaps with long lines are not missed.
+            if last.is_none_or(|prev| events[i].1.end > events[prev].1.end) {
+                last = Some(i);
+            }
+        }
+    }
+}
+
+/// Simplifies font or font file name for comparison: `Open Sans_B0.ttf` is `opensans`.
+fn font_key(name: &str) -> String {
+    let name = name.trim_start_matches('@');
+    let stem = match name.rsplit_once('.') {
+        Some((stem, ext)) if matches!(ext.to_ascii_lowercase().as_str(), "ttf" | "otf" | "ttc" | "otc") => stem,
+        _ => name,
+    };
+    // Aegisub appends `_0`, `_B0`, `_I0`, `_BI0` to file names of embedded fonts.
+    let stem = match stem.rsplit_once('_') {
+        Some((base, suffix)) if suffix.ends_with(|c: char| c.is_ascii_digit()) && suffix.len() <= 3 => base,
+        _ => stem,
+    };
+    stem.chars().filter(|x| x.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+fn check_fonts(linter: &mut Linter, ass: &Ass, fonts: Vec<String>) {
+    let embedded: HashSet<String> = ass.fonts.iter().map(|x| font_key(&x.name)).collect();
+    let mut reported = HashSet::new();
+    for font in fonts {
+        let key = font_key(&font);
+        if !embedded.contains(&key) && reported.insert(key) {
+            linter.report(Rule::FontNotEmbedded, Location::Script, format!("font {:?} is not embedded", font));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STYLE: &str = "Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1";
+
+    fn undefined_style_fix(style: &str) -> Vec<Edit> {
+        let data = format!(
+            "[V4+ Styles]\nStyle: {},{}\n\n[Events]\nDialogue: 0,0:00:00.00,0:00:01.00,Missing,,0,0,0,,a\n",
+            style, STYLE
+        );
+        let ass = crate::parse_str(&data).unwrap();
+        let findings = lint(&ass, &LintConfig::default());
+        let finding = findings.into_iter().find(|x| x.rule == Rule::UndefinedStyle).unwrap();
+        assert_eq!(finding.location, Location::Event(0));
+        finding.fix
+    }
+
+    #[test]
+    fn undefined_style() {
+        assert_eq!(undefined_style_fix("Default"), vec![Edit::SetStyle { event: 0, style: "Default".to_string() }]);
+        assert!(undefined_style_fix("Other").is_empty());
+    }
+
+    /// Lints a script with a 640x480 resolution, a `Default` style and `events` lines.
+    fn findings(events: &str) -> Vec<Finding> {
+        let data = format!(
+            "[Script Info]\nPlayResX: 640\nPlayResY: 480\n\n[V4+ Styles]\nStyle: Default,{}\n\n[Events]\n{}",
+            STYLE, events
+        );
+        let ass = crate::parse_str(&data).unwrap();
+        lint(&ass, &LintConfig::default()).into_iter().filter(|x| x.rule != Rule::FontNotEmbedded).collect()
+    }
+
+    fn rules(events: &str) -> Vec<(Rule, Location)> {
+        findings(events).into_iter().map(|x| (x.rule, x.location)).collect()
+    }
+
+    fn dialogue(start: &str, end: &str, style: &str, text: &str) -> String {
+        format!("Dialogue: 0,0:00:{},0:00:{},{},,0,0,0,,{}\n", start, end, style, text)
+    }
+
+    #[test]
+    fn clean_script() {
+        assert!(rules(&dialogue("00.00", "02.00", "Default", "{\\an8\\pos(1,2)\\c&H0000FF&}a")).is_empty());
+    }
+
+    #[test]
+    fn overlap() {
+        let events = [
+            dialogue("00.00", "05.00", "Default", "a"),
+            dialogue("01.00", "02.00", "Default", "b"),
+            dialogue("03.00", "04.00", "Default", "c"),
+            // Other layer, style, comments and touching events don't overlap.
+            "Dialogue: 1,0:00:01.00,0:00:02.00,Default,,0,0,0,,d\n".to_string(),
+            "Comment: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,e\n".to_string(),
+            dialogue("05.00", "06.00", "Default", "f"),
+        ];
+        let findings = findings(&events.concat());
+        let overlaps: Vec<_> = findings.iter().filter(|x| x.rule == Rule::Overlap).map(|x| x.location).collect();
+        assert_eq!(overlaps, vec![Location::Event(1), Location::Event(2)]);
+        // The long event is reported as the one the later overlap is with.
+        assert!(findings[1].message.contains("0:00:00.00"));
+    }
+
+    #[test]
+    fn characters_per_second() {
+        // 26 letters in one second, whitespace and tags are not counted.
+        let text = "{\\b1}abcdefghijklm nopqrstuvwxyz";
+        let expected = vec![(Rule::CharactersPerSecond, Location::Event(0))];
+        assert_eq!(rules(&dialogue("00.00", "01.00", "Default", text)), expected);
+        assert!(rules(&dialogue("00.00", "02.00", "Default", text)).is_empty());
+    }
+
+    #[test]
+    fn unbalanced_braces() {
+        let findings = findings(&dialogue("00.00", "01.00", "Default", "a}b{\\i1"));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, Rule::UnbalancedBraces);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(
+            findings[0].fix,
+            vec![
+                Edit::ReplaceText { event: 0, range: 1..2, text: String::new() },
+                Edit::ReplaceText { event: 0, range: 7..7, text: "}".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn tags() {
+        let rules = |text: &str| -> Vec<Rule> {
+            rules(&dialogue("00.00", "01.00", "Default", text)).into_iter().map(|x| x.0).collect()
+        };
+        assert_eq!(rules("{\\foo1}a"), vec![Rule::UnknownTag]);
+        assert_eq!(rules("{\\pos(1)}a"), vec![Rule::MalformedTag]);
+        assert_eq!(rules("{\\an10\\c&HXYZ&\\fs(1)}a"), vec![Rule::MalformedTag; 3]);
+        // Nested tags of `\t` are checked too.
+        assert_eq!(rules("{\\t(0,1,\\zz1)}a"), vec![Rule::UnknownTag]);
+        assert_eq!(rules("{\\t(x,\\fs1)}a"), vec![Rule::MalformedTag]);
+        assert!(rules("{\\clip(m 0 0 l 1 1)\\fs\\alpha&H80&}a").is_empty());
+    }
+
+    #[test]
+    fn font_not_embedded() {
+        let data = format!(
+            "[V4+ Styles]\nStyle: Default,{}\n\n[Events]\n{}\n[Fonts]\nfontname: arial_B0.ttf\n!!!!\n",
+            STYLE,
+            dialogue("00.00", "01.00", "Default", "{\\fnOpen Sans}a{\\fnopen sans}b{\\fnArial}c"),
+        );
+        let ass = crate::parse_str(&data).unwrap();
+        let findings: Vec<_> =
+            lint(&ass, &LintConfig::default()).into_iter().filter(|x| x.rule == Rule::FontNotEmbedded).collect();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].location, Location::Script);
+        assert_eq!(findings[0].message, "font \"Open Sans\" is not embedded");
+    }
+
+    #[test]
+    fn missing_resolution() {
+        let ass = crate::parse_str("[Script Info]\nPlayResX: 640\n\n[Events]\n").unwrap();
+        let findings = lint(&ass, &LintConfig::default());
+        assert_eq!(findings.iter().map(|x| x.rule).collect::<Vec<_>>(), vec![Rule::MissingResolution]);
+        let mut config = LintConfig::default();
+        config.disable(Rule::MissingResolution);
+        assert!(lint(&ass, &config).is_empty());
+    }
+
+    #[test]
+    fn duplicate_style() {
+        let data = format!(
+            "[Script Info]\nPlayResX: 640\nPlayResY: 480\n\n[V4+ Styles]\nStyle: A,{0}\nStyle: B,{0}\nStyle: A,{0}\n\n[Events]\n",
+            STYLE
+        );
+        let ass = crate::parse_str(&data).unwrap();
+        let findings = lint(&ass, &LintConfig::default());
+        let rules: Vec<_> = findings.iter().filter(|x| x.rule != Rule::FontNotEmbedded).map(|x| (x.rule, x.location)).collect();
+        assert_eq!(rules, vec![(Rule::DuplicateStyle, Location::Style(2))]);
+    }
+
+    #[test]
+    fn duplicate_event() {
+        let a = dialogue("00.00", "01.00", "Default", "a");
+        let b = dialogue("02.00", "03.00", "Default", "b");
+        let comment = "Comment: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,a\n";
+        let findings = findings(&[a.as_str(), &b, comment, &a, &b, &a].concat());
+        let duplicates: Vec<_> = findings.iter().filter(|x| x.rule == Rule::DuplicateEvent).collect();
+        let locations: Vec<_> = duplicates.iter().map(|x| x.location).collect();
+        assert_eq!(locations, vec![Location::Event(3), Location::Event(4), Location::Event(5)]);
+        assert_eq!(duplicates[0].fix, vec![Edit::RemoveEvent { event: 3 }]);
+        assert_eq!(duplicates[1].message, "duplicate of event at 0:00:02.00");
+    }
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+use crate::structure::base_types::LineField;
+use crate::structure::event::{Event, EventKey};
+use crate::structure::{Ass, InvalidValue, EVENT_FORMAT};
+
+/// Columns of an event stored in a Matroska block. Times are stored in the block itself.
+/// SSA tracks have `Marked` in place of `Layer`.
+pub const BLOCK_FORMAT: &str = "ReadOrder, Layer, Style, Name, MarginL, MarginR, MarginV, Effect, Text";
+
+impl<'a> Event<'a> {
+    /// Parses block data in [`BLOCK_FORMAT`]. Returns ReadOrder (position of the event in the script) and the event.
+    pub fn from_block(data: &'a str, start: Duration, end: Duration) -> Result<(u64, Self), InvalidValue> {
+        let mut fields = data.splitn(9, ',');
+        let mut next = || fields.next().map(LineField::new).ok_or(InvalidValue);
+        let read_order: u64 = next()?.try_into()?;
+        let layer = next()?;
+        let layer: u32 = match layer.value().trim().strip_prefix("Marked=") {
+            Some(marked) => LineField::new(marked).try_into()?,
+            None => layer.try_into()?,
+        };
+        let event = Event {
+            layer,
+            start,
+            end,
+            style: next()?.into(),
+            actor: next()?.into(),
+            margin_l: next()?.try_into()?,
+            margin_r: next()?.try_into()?,
+            margin_v: next()?.try_into()?,
+            effect: next()?.into(),
+            text: next()?.into(),
+        };
+        Ok((read_order, event))
+    }
+
+    /// Block data in [`BLOCK_FORMAT`]. Start and duration are stored in the block separately.
+    pub fn to_block(&self, read_order: u64) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{}",
+            read_order,
+            self.layer,
+            self.style,
+            self.actor,
+            self.margin_l,
+            self.margin_r,
+            self.margin_v,
+            self.effect,
+            self.text,
+        )
+    }
+}
+
+impl Ass<'_> {
+    /// CodecPrivate of a Matroska ASS track: the script without events.
+    pub fn matroska_header(&self) -> String {
+        format!("{}\n{}\n[Events]\nFormat: {}\n", self.info, self.styles, EVENT_FORMAT)
+    }
+
+    /// Blocks of a Matroska ASS track: start, duration and data of each `Dialogue` event, sorted by start.
+    pub fn matroska_blocks(&self) -> Vec<(Duration, Duration, String)> {
+        let mut blocks: Vec<_> = self
+            .events
+            .events
+            .iter()
+            .filter(|(key, _)| *key == EventKey::Dialogue)
+            .enumerate()
+            .map(|(i, (_, e))| (e.start, e.end.saturating_sub(e.start), e.to_block(i as u64)))
+            .collect();
+        blocks.sort_by_key(|x| x.0);
+        blocks
+    }
+}
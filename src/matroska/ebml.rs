@@ -0,0 +1,97 @@
+use crate::matroska::MatroskaError;
+
+/// Element with its ID (including the length marker, as in the specification) and content.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Element<'d> {
+    pub id: u32,
+    pub data: &'d [u8],
+    /// Size is unknown, `data` spans to the end of the parent.
+    pub unknown_size: bool,
+}
+
+impl<'d> Element<'d> {
+    pub fn uint(&self) -> u64 {
+        self.data.iter().take(8).fold(0, |v, b| v << 8 | *b as u64)
+    }
+
+    pub fn string(&self) -> String {
+        let end = self.data.iter().position(|x| *x == 0).unwrap_or(self.data.len());
+        String::from_utf8_lossy(&self.data[..end]).into_owned()
+    }
+
+    pub fn children(&self) -> Reader<'d> {
+        Reader::new(self.data)
+    }
+}
+
+/// Reads a sequence of elements from a byte slice.
+#[derive(Debug, Clone)]
+pub(crate) struct Reader<'d> {
+    data: &'d [u8],
+    pos: usize,
+}
+
+/// Reads variable length integer. Returns value with marker bit cleared, and its length.
+pub(crate) fn read_vint(data: &[u8]) -> Result<(u64, usize), MatroskaError> {
+    let first = *data.first().ok_or(MatroskaError::Truncated)?;
+    let len = first.leading_zeros() as usize + 1;
+    if len > 8 {
+        return Err(MatroskaError::InvalidData);
+    }
+    let bytes = data.get(..len).ok_or(MatroskaError::Truncated)?;
+    let value = bytes[1..].iter().fold((first as u64) & (0xff >> len), |v, b| v << 8 | *b as u64);
+    Ok((value, len))
+}
+
+impl<'d> Reader<'d> {
+    pub fn new(data: &'d [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// ID of the next element without moving.
+    pub fn peek_id(&self) -> Option<u32> {
+        let (_, len) = read_vint(&self.data[self.pos..]).ok()?;
+        if len > 4 {
+            return None;
+        }
+        Some(self.data[self.pos..self.pos + len].iter().fold(0, |v, b| v << 8 | *b as u32))
+    }
+
+    /// Reads the next element header and content. Elements of unknown size take the rest of data.
+    pub fn next_element(&mut self) -> Option<Result<Element<'d>, MatroskaError>> {
+        if self.pos >= self.data.len() {
+            return None;
+        }
+        Some(self.read_element())
+    }
+
+    fn read_element(&mut self) -> Result<Element<'d>, MatroskaError> {
+        let id = self.peek_id().ok_or(MatroskaError::InvalidData)?;
+        let id_len = 4 - id.leading_zeros() as usize / 8;
+        let (size, size_len) = read_vint(&self.data[self.pos + id_len..])?;
+        let start = self.pos + id_len + size_len;
+        // All value bits set means unknown size.
+        let unknown_size = size == (1u64 << (7 * size_len)) - 1;
+        let end = if unknown_size {
+            self.data.len()
+        } else {
+            start.checked_add(size as usize).filter(|x| *x <= self.data.len()).ok_or(MatroskaError::Truncated)?
+        };
+        self.pos = end;
+        Ok(Element { id, data: &self.data[start..end], unknown_size })
+    }
+
+    /// Moves to the end of the header of an element of unknown size, so its children can be read
+    /// one by one from this reader.
+    pub fn enter(&mut self, element: &Element<'d>) {
+        self.pos = self.data.len() - element.data.len();
+    }
+}
+
+impl<'d> Iterator for Reader<'d> {
+    type Item = Result<Element<'d>, MatroskaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_element()
+    }
+}
@@ -0,0 +1,520 @@
+//! Reading ASS subtitles from Matroska (`.mkv`, `.mks`) files.
+//!
+//! Only what is needed for subtitles is read: tracks, clusters with their blocks and attachments.
+//! Video and audio data is skipped.
+
+mod block;
+mod ebml;
+
+pub use block::BLOCK_FORMAT;
+
+use std::time::Duration;
+use crate::matroska::ebml::{read_vint, Element, Reader};
+use crate::structure::attachment::Attachment;
+use crate::structure::event::{Event, EventKey};
+use crate::structure::{Ass, InvalidValue};
+
+const EBML: u32 = 0x1A45DFA3;
+const SEGMENT: u32 = 0x18538067;
+const INFO: u32 = 0x1549A966;
+const TIMESTAMP_SCALE: u32 = 0x2AD7B1;
+const TRACKS: u32 = 0x1654AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63A2;
+const NAME: u32 = 0x536E;
+const LANGUAGE: u32 = 0x22B59C;
+const CONTENT_ENCODINGS: u32 = 0x6D80;
+const CONTENT_ENCODING: u32 = 0x6240;
+const CONTENT_ENCODING_ORDER: u32 = 0x5031;
+const CONTENT_ENCODING_SCOPE: u32 = 0x5032;
+const CONTENT_ENCODING_TYPE: u32 = 0x5033;
+const CONTENT_COMPRESSION: u32 = 0x5034;
+const CONTENT_COMP_ALGO: u32 = 0x4254;
+const CONTENT_COMP_SETTINGS: u32 = 0x4255;
+const CLUSTER: u32 = 0x1F43B675;
+const CLUSTER_TIMESTAMP: u32 = 0xE7;
+const SIMPLE_BLOCK: u32 = 0xA3;
+const BLOCK_GROUP: u32 = 0xA0;
+const BLOCK: u32 = 0xA1;
+const BLOCK_DURATION: u32 = 0x9B;
+const ATTACHMENTS: u32 = 0x1941A469;
+const ATTACHED_FILE: u32 = 0x61A7;
+const FILE_NAME: u32 = 0x466E;
+const FILE_MIME_TYPE: u32 = 0x4660;
+const FILE_DATA: u32 = 0x465C;
+/// Children of Segment. Any of them ends a cluster of unknown size.
+const TOP_LEVEL: [u32; 10] = [
+    EBML, SEGMENT, 0x114D9B74, INFO, TRACKS, CLUSTER, 0x1C53BB6B, ATTACHMENTS, 0x1043A770, 0x1254C367,
+];
+
+/// Codec IDs of ASS and SSA tracks, including old ones.
+const CODECS: [&str; 4] = ["S_TEXT/ASS", "S_TEXT/SSA", "S_ASS", "S_SSA"];
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MatroskaError {
+    NotMatroska,
+    Truncated,
+    InvalidData,
+    /// Track data is encrypted or compressed with something other than zlib or header stripping.
+    UnsupportedEncoding,
+}
+
+/// Subtitle frame of a track, with absolute time.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Block {
+    pub start: Duration,
+    pub duration: Option<Duration>,
+    /// Event in [`BLOCK_FORMAT`].
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SubtitleTrack {
+    pub number: u64,
+    pub codec_id: String,
+    pub name: Option<String>,
+    pub language: Option<String>,
+    /// Script without events, from CodecPrivate.
+    pub header: String,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FileAttachment {
+    pub name: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+impl FileAttachment {
+    pub fn is_font(&self) -> bool {
+        let mime = self.mime_type.to_ascii_lowercase();
+        let name = self.name.to_ascii_lowercase();
+        mime.starts_with("font/")
+            || matches!(
+                mime.as_str(),
+                "application/x-truetype-font" | "application/vnd.ms-opentype" | "application/font-sfnt" | "application/x-font-ttf" | "application/x-font-otf"
+            )
+            || [".ttf", ".otf", ".ttc", ".otc"].iter().any(|x| name.ends_with(x))
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Matroska {
+    /// ASS and SSA tracks.
+    pub tracks: Vec<SubtitleTrack>,
+    pub attachments: Vec<FileAttachment>,
+}
+
+/// Content encoding of a track, applied in reverse to get original data.
+#[derive(Debug, Clone)]
+enum Encoding {
+    Zlib,
+    HeaderStripping(Vec<u8>),
+}
+
+#[derive(Debug, Clone)]
+struct Encodings {
+    /// Encodings to undo for blocks, in order.
+    blocks: Vec<Encoding>,
+    private: Vec<Encoding>,
+}
+
+impl Encodings {
+    fn parse(element: &Element) -> Result<Self, MatroskaError> {
+        let mut encodings = vec![];
+        for encoding in element.children() {
+            let encoding = encoding?;
+            if encoding.id != CONTENT_ENCODING {
+                continue;
+            }
+            let (mut order, mut scope, mut kind, mut algo, mut settings) = (0, 1, 0, 0, vec![]);
+            for child in encoding.children() {
+                let child = child?;
+                match child.id {
+                    CONTENT_ENCODING_ORDER => order = child.uint(),
+                    CONTENT_ENCODING_SCOPE => scope = child.uint(),
+                    CONTENT_ENCODING_TYPE => kind = child.uint(),
+                    CONTENT_COMPRESSION => {
+                        for child in child.children() {
+                            let child = child?;
+                            match child.id {
+                                CONTENT_COMP_ALGO => algo = child.uint(),
+                                CONTENT_COMP_SETTINGS => settings = child.data.to_vec(),
+                                _ => {}
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            let encoding = match (kind, algo) {
+                (0, 0) => Encoding::Zlib,
+                (0, 3) => Encoding::HeaderStripping(settings),
+                _ => return Err(MatroskaError::UnsupportedEncoding),
+            };
+            encodings.push((order, scope, encoding));
+        }
+        // Encodings are undone starting from the highest order.
+        encodings.sort_by_key(|x| std::cmp::Reverse(x.0));
+        let select = |bit: u64| encodings.iter().filter(|x| x.1 & bit != 0).map(|x| x.2.clone()).collect();
+        Ok(Self { blocks: select(1), private: select(2) })
+    }
+
+    fn decode(encodings: &[Encoding], data: &[u8]) -> Result<Vec<u8>, MatroskaError> {
+        let mut data = data.to_vec();
+        for encoding in encodings {
+            data = match encoding {
+                Encoding::Zlib => miniz_oxide::inflate::decompress_to_vec_zlib(&data).map_err(|_| MatroskaError::InvalidData)?,
+                Encoding::HeaderStripping(header) => [header.as_slice(), &data].concat(),
+            };
+        }
+        Ok(data)
+    }
+}
+
+fn text(data: Vec<u8>) -> String {
+    let s = String::from_utf8(data).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned());
+    s.trim_end_matches('\0').to_string()
+}
+
+/// Reader state shared by all clusters.
+struct State {
+    scale: u64,
+    /// Track number, index in `tracks` and its encodings.
+    encodings: Vec<(u64, usize, Encodings)>,
+    matroska: Matroska,
+}
+
+impl State {
+    fn track(&mut self, entry: &Element) -> Result<(), MatroskaError> {
+        let (mut number, mut codec_id, mut private, mut name, mut language, mut encodings) =
+            (0, String::new(), vec![], None, None, None);
+        for child in entry.children() {
+            let child = child?;
+            match child.id {
+                TRACK_NUMBER => number = child.uint(),
+                CODEC_ID => codec_id = child.string(),
+                CODEC_PRIVATE => private = child.data.to_vec(),
+                NAME => name = Some(child.string()),
+                LANGUAGE => language = Some(child.string()),
+                CONTENT_ENCODINGS => encodings = Some(Encodings::parse(&child)?),
+                _ => {}
+            }
+        }
+        if !CODECS.contains(&codec_id.as_str()) {
+            return Ok(());
+        }
+        let encodings = encodings.unwrap_or(Encodings { blocks: vec![], private: vec![] });
+        let header = text(Encodings::decode(&encodings.private, &private)?);
+        self.encodings.push((number, self.matroska.tracks.len(), encodings));
+        self.matroska.tracks.push(SubtitleTrack { number, codec_id, name, language, header, blocks: vec![] });
+        Ok(())
+    }
+
+    fn block(&mut self, cluster_time: u64, data: &[u8], duration: Option<u64>) -> Result<(), MatroskaError> {
+        let (number, len) = read_vint(data)?;
+        let Some((_, index, encodings)) = self.encodings.iter().find(|x| x.0 == number) else { return Ok(()) };
+        let header = data.get(len..len + 3).ok_or(MatroskaError::Truncated)?;
+        // Subtitle blocks are never laced in practice, skip ones that are.
+        if header[2] & 0x06 != 0 {
+            return Ok(());
+        }
+        let relative = i16::from_be_bytes([header[0], header[1]]) as i64;
+        let ticks = (cluster_time as i64 + relative).max(0) as u64;
+        let nanos = |ticks: u64| Duration::from_nanos(ticks.saturating_mul(self.scale));
+        let block = Block {
+            start: nanos(ticks),
+            duration: duration.map(nanos),
+            data: text(Encodings::decode(&encodings.blocks, &data[len + 3..])?),
+        };
+        self.matroska.tracks[*index].blocks.push(block);
+        Ok(())
+    }
+
+    /// Handles a child of Cluster, updating cluster timestamp.
+    fn cluster_child(&mut self, child: &Element, time: &mut u64) -> Result<(), MatroskaError> {
+        match child.id {
+            CLUSTER_TIMESTAMP => *time = child.uint(),
+            SIMPLE_BLOCK => self.block(*time, child.data, None)?,
+            BLOCK_GROUP => {
+                let (mut block, mut duration) = (None, None);
+                for child in child.children() {
+                    let child = child?;
+                    match child.id {
+                        BLOCK => block = Some(child.data),
+                        BLOCK_DURATION => duration = Some(child.uint()),
+                        _ => {}
+                    }
+                }
+                if let Some(block) = block {
+                    self.block(*time, block, duration)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn attachments(&mut self, element: &Element) -> Result<(), MatroskaError> {
+        for file in element.children() {
+            let file = file?;
+            if file.id != ATTACHED_FILE {
+                continue;
+            }
+            let mut attachment = FileAttachment { name: String::new(), mime_type: String::new(), data: vec![] };
+            for child in file.children() {
+                let child = child?;
+                match child.id {
+                    FILE_NAME => attachment.name = child.string(),
+                    FILE_MIME_TYPE => attachment.mime_type = child.string(),
+                    FILE_DATA => attachment.data = child.data.to_vec(),
+                    _ => {}
+                }
+            }
+            self.matroska.attachments.push(attachment);
+        }
+        Ok(())
+    }
+}
+
+impl Matroska {
+    /// Reads ASS tracks and attachments of the first segment of a Matroska file.
+    pub fn read(data: &[u8]) -> Result<Self, MatroskaError> {
+        let mut reader = Reader::new(data);
+        match reader.next_element() {
+            Some(Ok(header)) if header.id == EBML => {}
+            _ => return Err(MatroskaError::NotMatroska),
+        }
+        let segment = reader
+            .find(|x| x.as_ref().map_or(true, |x| x.id == SEGMENT))
+            .ok_or(MatroskaError::NotMatroska)??;
+        let mut state = State { scale: 1_000_000, encodings: vec![], matroska: Matroska::default() };
+        let mut reader = segment.children();
+        while let Some(element) = reader.next_element() {
+            let element = element?;
+            match element.id {
+                INFO => {
+                    for child in element.children() {
+                        let child = child?;
+                        if child.id == TIMESTAMP_SCALE && child.uint() != 0 {
+                            state.scale = child.uint();
+                        }
+                    }
+                }
+                TRACKS => {
+                    for entry in element.children() {
+                        let entry = entry?;
+                        if entry.id == TRACK_ENTRY {
+                            state.track(&entry)?;
+                        }
+                    }
+                }
+                CLUSTER if element.unknown_size => {
+                    // Cluster continues until the next top level element.
+                    reader.enter(&element);
+                    let mut time = 0;
+                    while reader.peek_id().is_some_and(|x| !TOP_LEVEL.contains(&x)) {
+                        let Some(child) = reader.next_element() else { break };
+                        let child = child?;
+                        if child.unknown_size {
+                            return Err(MatroskaError::InvalidData);
+                        }
+                        state.cluster_child(&child, &mut time)?;
+                    }
+                }
+                CLUSTER => {
+                    let mut time = 0;
+                    for child in element.children() {
+                        state.cluster_child(&child?, &mut time)?;
+                    }
+                }
+                ATTACHMENTS => state.attachments(&element)?,
+                _ => {}
+            }
+        }
+        Ok(state.matroska)
+    }
+
+    /// Builds script from a track, with font attachments of the file in `[Fonts]`.
+    pub fn to_ass(&self, track: usize) -> Result<Ass<'_>, InvalidValue> {
+        let track = self.tracks.get(track).ok_or(InvalidValue)?;
+        let fonts = self.attachments.iter().filter(|x| x.is_font());
+        let mut ass = track.to_ass()?;
+        ass.fonts.extend(fonts.map(|x| Attachment::encode(x.name.as_str(), &x.data)));
+        Ok(ass)
+    }
+}
+
+impl SubtitleTrack {
+    /// Builds script from header and blocks, with events in their original order.
+    pub fn to_ass(&self) -> Result<Ass<'_>, InvalidValue> {
+        let mut ass = crate::parse_str(&self.header)?;
+        let mut events: Vec<(u64, Event)> = vec![];
+        for block in &self.blocks {
+            let end = block.start + block.duration.unwrap_or_default();
+            events.push(Event::from_block(&block.data, block.start, end)?);
+        }
+        events.sort_by_key(|x| x.0);
+        ass.events.events = events.into_iter().map(|x| (EventKey::Dialogue, x.1)).collect();
+        Ok(ass)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "[Script Info]\nScriptType: v4.00+\n\n[V4+ Styles]\n\
+        Style: Default,Arial,48,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1\n\n\
+        [Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n";
+
+    fn element(id: u32, data: &[u8]) -> Vec<u8> {
+        let mut v: Vec<u8> = id.to_be_bytes().into_iter().skip_while(|x| *x == 0).collect();
+        // 8 byte size, the longest form.
+        v.push(0x01);
+        v.extend_from_slice(&(data.len() as u64).to_be_bytes()[1..]);
+        v.extend_from_slice(data);
+        v
+    }
+
+    fn unknown_size(id: u32) -> Vec<u8> {
+        let mut v = id.to_be_bytes().to_vec();
+        v.push(0xFF);
+        v
+    }
+
+    fn uint(id: u32, n: u64) -> Vec<u8> {
+        element(id, &n.to_be_bytes())
+    }
+
+    fn block_group(track: u8, relative: i16, data: &[u8], duration: u64) -> Vec<u8> {
+        let mut block = vec![0x80 | track];
+        block.extend(relative.to_be_bytes());
+        block.push(0);
+        block.extend_from_slice(data);
+        element(BLOCK_GROUP, &[element(BLOCK, &block), uint(BLOCK_DURATION, duration)].concat())
+    }
+
+    fn zlib(s: &str) -> Vec<u8> {
+        miniz_oxide::deflate::compress_to_vec_zlib(s.as_bytes(), 6)
+    }
+
+    /// File with a video track and an ASS track compressed with zlib, with CodecPrivate also header-stripped.
+    fn file() -> Vec<u8> {
+        let zlib_encoding = element(CONTENT_ENCODING, &[uint(CONTENT_ENCODING_SCOPE, 1), element(CONTENT_COMPRESSION, &uint(CONTENT_COMP_ALGO, 0))].concat());
+        let compression = [uint(CONTENT_COMP_ALGO, 3), element(CONTENT_COMP_SETTINGS, b"[Script")].concat();
+        let strip_encoding = element(
+            CONTENT_ENCODING,
+            &[uint(CONTENT_ENCODING_ORDER, 1), uint(CONTENT_ENCODING_SCOPE, 2), element(CONTENT_COMPRESSION, &compression)].concat(),
+        );
+        let subtitles = element(
+            TRACK_ENTRY,
+            &[
+                uint(TRACK_NUMBER, 3),
+                element(CODEC_ID, b"S_TEXT/ASS"),
+                element(CODEC_PRIVATE, &HEADER.as_bytes()[7..]),
+                element(LANGUAGE, b"eng"),
+                element(NAME, b"Full"),
+                element(CONTENT_ENCODINGS, &[zlib_encoding, strip_encoding].concat()),
+            ]
+            .concat(),
+        );
+        let video = element(TRACK_ENTRY, &[uint(TRACK_NUMBER, 1), element(CODEC_ID, b"V_MPEG4/ISO/AVC")].concat());
+        let cluster = element(
+            CLUSTER,
+            &[
+                uint(CLUSTER_TIMESTAMP, 1000),
+                block_group(3, 500, &zlib("1,0,Default,,0,0,0,,Second"), 1000),
+                block_group(1, 0, b"video", 0),
+                block_group(3, 0, &zlib("0,1,Default,Bob,0,0,0,,{\\b1}First, line"), 2000),
+            ]
+            .concat(),
+        );
+        let mut unknown_cluster = unknown_size(CLUSTER);
+        unknown_cluster.extend([uint(CLUSTER_TIMESTAMP, 5000), block_group(3, -100, &zlib("2,0,Default,,0,0,0,,Third"), 300)].concat());
+        let font = element(
+            ATTACHED_FILE,
+            &[element(FILE_NAME, b"MyFont.ttf"), element(FILE_MIME_TYPE, b"application/x-truetype-font"), element(FILE_DATA, &[0, 1, 2, 255])]
+                .concat(),
+        );
+        let image = element(ATTACHED_FILE, &[element(FILE_NAME, b"cover.jpg"), element(FILE_MIME_TYPE, b"image/jpeg"), element(FILE_DATA, &[1])].concat());
+        let mut segment = unknown_size(SEGMENT);
+        segment.extend(element(INFO, &uint(TIMESTAMP_SCALE, 1_000_000)));
+        segment.extend(element(TRACKS, &[video, subtitles].concat()));
+        segment.extend(cluster);
+        segment.extend(unknown_cluster);
+        // Ends the cluster of unknown size.
+        segment.extend(element(ATTACHMENTS, &[font, image].concat()));
+        [element(EBML, &element(0x4282, b"matroska")), segment].concat()
+    }
+
+    #[test]
+    fn vint() {
+        assert_eq!(read_vint(&[0x81]), Ok((1, 1)));
+        assert_eq!(read_vint(&[0x40, 0x02]), Ok((2, 2)));
+        assert_eq!(read_vint(&[0x40]), Err(MatroskaError::Truncated));
+        assert_eq!(read_vint(&[0x00]), Err(MatroskaError::InvalidData));
+    }
+
+    #[test]
+    fn elements() {
+        let data = [element(TRACK_NUMBER, &[5]), unknown_size(CLUSTER), vec![0xE7, 0x81, 7]].concat();
+        let mut reader = Reader::new(&data);
+        let first = reader.next_element().unwrap().unwrap();
+        assert_eq!((first.id, first.uint(), first.unknown_size), (TRACK_NUMBER, 5, false));
+        assert_eq!(reader.peek_id(), Some(CLUSTER));
+        let cluster = reader.next_element().unwrap().unwrap();
+        assert!(cluster.unknown_size);
+        let child = cluster.children().next().unwrap().unwrap();
+        assert_eq!((child.id, child.uint()), (CLUSTER_TIMESTAMP, 7));
+        assert!(reader.next_element().is_none());
+        assert_eq!(Reader::new(&[0x81, 0x85, 1]).next_element().unwrap().unwrap_err(), MatroskaError::Truncated);
+    }
+
+    #[test]
+    fn read() {
+        let mkv = Matroska::read(&file()).unwrap();
+        assert_eq!(mkv.tracks.len(), 1);
+        let track = &mkv.tracks[0];
+        assert_eq!((track.number, track.codec_id.as_str()), (3, "S_TEXT/ASS"));
+        assert_eq!((track.name.as_deref(), track.language.as_deref()), (Some("Full"), Some("eng")));
+        assert_eq!(track.header, HEADER);
+        let blocks: Vec<_> = track.blocks.iter().map(|x| (x.start.as_millis(), x.duration.unwrap().as_millis())).collect();
+        assert_eq!(blocks, vec![(1500, 1000), (1000, 2000), (4900, 300)]);
+        assert_eq!(track.blocks[2].data, "2,0,Default,,0,0,0,,Third");
+        let names: Vec<_> = mkv.attachments.iter().map(|x| (x.name.as_str(), x.is_font())).collect();
+        assert_eq!(names, vec![("MyFont.ttf", true), ("cover.jpg", false)]);
+    }
+
+    #[test]
+    fn to_ass() {
+        let mkv = Matroska::read(&file()).unwrap();
+        let ass = mkv.to_ass(0).unwrap();
+        let texts: Vec<_> = ass.events.events.iter().map(|x| x.1.text.as_ref()).collect();
+        assert_eq!(texts, vec!["{\\b1}First, line", "Second", "Third"]);
+        let first = &ass.events.events[0].1;
+        assert_eq!((first.layer, first.actor.as_ref(), first.end), (1, "Bob", Duration::from_millis(3000)));
+        assert_eq!(ass.fonts.len(), 1);
+        assert_eq!(ass.fonts[0].decode(), vec![0, 1, 2, 255]);
+        assert!(mkv.to_ass(1).is_err());
+    }
+
+    #[test]
+    fn blocks_round_trip() {
+        let mkv = Matroska::read(&file()).unwrap();
+        let ass = mkv.to_ass(0).unwrap();
+        let blocks = ass.matroska_blocks();
+        assert_eq!(blocks[0], (Duration::from_millis(1000), Duration::from_millis(2000), "0,1,Default,Bob,0,0,0,,{\\b1}First, line".to_string()));
+        assert!(ass.matroska_header().ends_with("[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n"));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(Matroska::read(b"not mkv"), Err(MatroskaError::NotMatroska));
+        let file = file();
+        assert!(Matroska::read(&file[..file.len() / 2]).is_err());
+    }
+}
@@ -0,0 +1,263 @@
+use std::borrow::Cow;
+use std::time::Duration;
+use crate::structure::event::Event;
+use crate::structure::state::{collect_runs, transform_progress, Run};
+use crate::structure::tags::{self, Block, OverrideItem, Tag};
+use crate::structure::Styles;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Clip<'a> {
+    Rect { x1: f64, y1: f64, x2: f64, y2: f64 },
+    Vector { scale: u32, commands: Cow<'a, str> },
+}
+
+/// State of an event at some moment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventFrame<'a> {
+    /// Time since the event start.
+    pub time: Duration,
+    /// Runs with `\t` and fades applied.
+    pub runs: Vec<Run<'a>>,
+    /// Position from `\pos` or `\move`.
+    pub position: Option<(f64, f64)>,
+    /// Rotation origin from `\org`.
+    pub origin: Option<(f64, f64)>,
+    pub clip: Option<Clip<'a>>,
+    /// Whether the clip is inverse (`\iclip`).
+    pub inverse_clip: bool,
+    /// Transparency added by `\fad` or `\fade`, where 0 is opaque and 255 is invisible.
+    pub fade: u8,
+}
+
+impl EventFrame<'_> {
+    /// Highlighted part of karaoke syllable of the run, from 0 to 1.
+    pub fn karaoke_progress(&self, run: &Run) -> Option<f64> {
+        run.karaoke.map(|x| x.progress(self.time))
+    }
+}
+
+fn clamp_progress(t: f64, t1: f64, t2: f64) -> f64 {
+    if t <= t1 {
+        0.0
+    } else if t >= t2 || t2 <= t1 {
+        1.0
+    } else {
+        (t - t1) / (t2 - t1)
+    }
+}
+
+/// Alpha of `\fade(a1,a2,a3,t1,t2,t3,t4)` at `t` milliseconds after the event start.
+fn fade_alpha(a: [f64; 3], t: [f64; 4], time: f64) -> f64 {
+    if time < t[0] {
+        a[0]
+    } else if time < t[1] {
+        a[0] + (a[1] - a[0]) * clamp_progress(time, t[0], t[1])
+    } else if time < t[2] {
+        a[1]
+    } else if time < t[3] {
+        a[1] + (a[2] - a[1]) * clamp_progress(time, t[2], t[3])
+    } else {
+        a[2]
+    }
+}
+
+fn add_alpha(alpha: u8, fade: u8) -> u8 {
+    (alpha as u32 + (255 - alpha as u32) * fade as u32 / 255) as u8
+}
+
+impl<'a> Event<'a> {
+    /// Evaluates event at `time` (script time, not relative to the event start).
+    pub fn at<'b>(&'b self, styles: &'b Styles<'_>, time: Duration) -> EventFrame<'b> {
+        let time = time.saturating_sub(self.start);
+        let duration = self.end.saturating_sub(self.start);
+        let t = time.as_millis() as f64;
+        let mut frame = EventFrame {
+            time,
+            runs: collect_runs(self, styles, Some(time)),
+            position: None,
+            origin: None,
+            clip: None,
+            inverse_clip: false,
+            fade: 0,
+        };
+        let mut fade_set = false;
+        let blocks = tags::parse(&self.text);
+        let tags = blocks.iter().flat_map(|x| match x {
+            Block::Override(items) => items.as_slice(),
+            _ => &[],
+        });
+        for item in tags {
+            let OverrideItem::Tag(tag) = item else { continue };
+            let n = |i: usize| tag.number(i);
+            match (tag.name, tag.args.len()) {
+                // Position, origin and fade are taken from the first tag only.
+                ("pos", 2) if frame.position.is_none() => {
+                    frame.position = n(0).zip(n(1));
+                }
+                ("move", 4 | 6) if frame.position.is_none() => {
+                    let (t1, mut t2) = (n(4).unwrap_or(0.0), n(5).unwrap_or(0.0));
+                    if t1 == 0.0 && t2 == 0.0 {
+                        t2 = duration.as_millis() as f64;
+                    }
+                    let k = clamp_progress(t, t1, t2);
+                    if let (Some(x1), Some(y1), Some(x2), Some(y2)) = (n(0), n(1), n(2), n(3)) {
+                        frame.position = Some((x1 + (x2 - x1) * k, y1 + (y2 - y1) * k));
+                    }
+                }
+                ("org", 2) if frame.origin.is_none() => {
+                    frame.origin = n(0).zip(n(1));
+                }
+                ("fad", 2) if !fade_set => {
+                    let end = duration.as_millis() as f64;
+                    let (fade_in, fade_out) = (n(0).unwrap_or(0.0), n(1).unwrap_or(0.0));
+                    let alpha = fade_alpha([255.0, 0.0, 255.0], [0.0, fade_in, end - fade_out, end], t);
+                    frame.fade = alpha.round().clamp(0.0, 255.0) as u8;
+                    fade_set = true;
+                }
+                ("fade", 7) if !fade_set => {
+                    let v: Vec<f64> = (0..7).map(|i| n(i).unwrap_or(0.0)).collect();
+                    let alpha = fade_alpha([v[0], v[1], v[2]], [v[3], v[4], v[5], v[6]], t);
+                    frame.fade = alpha.round().clamp(0.0, 255.0) as u8;
+                    fade_set = true;
+                }
+                ("clip" | "iclip", _) => {
+                    frame.inverse_clip = tag.name == "iclip";
+                    frame.clip = clip(tag);
+                }
+                ("t", _) => {
+                    // Only rectangular clips can be animated.
+                    let nested = tag.nested();
+                    let target = nested.iter().rev().find_map(|x| match x {
+                        OverrideItem::Tag(tag) if matches!(tag.name, "clip" | "iclip") => clip(tag),
+                        _ => None,
+                    });
+                    if let (Some(Clip::Rect { x1, y1, x2, y2 }), Some(Clip::Rect { x1: tx1, y1: ty1, x2: tx2, y2: ty2 })) =
+                        (&frame.clip, target)
+                    {
+                        let k = transform_progress(tag, time, duration);
+                        frame.clip = Some(Clip::Rect {
+                            x1: x1 + (tx1 - x1) * k,
+                            y1: y1 + (ty1 - y1) * k,
+                            x2: x2 + (tx2 - x2) * k,
+                            y2: y2 + (ty2 - y2) * k,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        if frame.fade != 0 {
+            for run in &mut frame.runs {
+                let style = &mut run.style;
+                for color in [
+                    &mut style.primary_color,
+                    &mut style.secondary_color,
+                    &mut style.outline_color,
+                    &mut style.background_color,
+                ] {
+                    color.a = add_alpha(color.a, frame.fade);
+                }
+            }
+        }
+        frame
+    }
+}
+
+fn clip<'a>(tag: &Tag<'a>) -> Option<Clip<'a>> {
+    match tag.args.len() {
+        4 => Some(Clip::Rect {
+            x1: tag.number(0)?,
+            y1: tag.number(1)?,
+            x2: tag.number(2)?,
+            y2: tag.number(3)?,
+        }),
+        1 => Some(Clip::Vector { scale: 1, commands: tag.args[0].clone() }),
+        2 => Some(Clip::Vector {
+            scale: tag.number(0)?.max(1.0) as u32,
+            commands: tag.args[1].clone(),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "[V4+ Styles]
+Style: Default,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1
+
+[Events]
+";
+
+    /// Evaluates an event from 1s to 3s at `ms` milliseconds after its start.
+    fn at<T>(text: &str, ms: u64, check: impl FnOnce(&EventFrame) -> T) -> T {
+        let data = format!("{}Dialogue: 0,0:00:01.00,0:00:03.00,Default,,0,0,0,,{}\n", SCRIPT, text);
+        let ass = crate::parse_str(&data).unwrap();
+        let frame = ass.events.events[0].1.at(&ass.styles, Duration::from_millis(1000 + ms));
+        check(&frame)
+    }
+
+    #[test]
+    fn position() {
+        let position = |text: &str, ms: u64| at(text, ms, |x| x.position);
+        assert_eq!(position("{\\pos(10,20)\\move(0,0,1,1)}a", 500), Some((10.0, 20.0)));
+        // Without times the move takes the whole event.
+        assert_eq!(position("{\\move(0,0,100,200)}a", 500), Some((25.0, 50.0)));
+        assert_eq!(position("{\\move(0,0,100,200)}a", 2000), Some((100.0, 200.0)));
+        let moving = "{\\move(0,0,100,0,500,1500)\\org(5,5)}a";
+        assert_eq!(position(moving, 250), Some((0.0, 0.0)));
+        assert_eq!(position(moving, 1000), Some((50.0, 0.0)));
+        assert_eq!(position(moving, 1800), Some((100.0, 0.0)));
+        assert_eq!(at(moving, 0, |x| x.origin), Some((5.0, 5.0)));
+        // Times before the event are clamped to its start.
+        let data = format!("{}Dialogue: 0,0:00:01.00,0:00:03.00,Default,,0,0,0,,{{\\move(0,0,100,200)}}a\n", SCRIPT);
+        let ass = crate::parse_str(&data).unwrap();
+        assert_eq!(ass.events.events[0].1.at(&ass.styles, Duration::ZERO).position, Some((0.0, 0.0)));
+    }
+
+    #[test]
+    fn fad() {
+        let fade = |ms: u64| at("{\\fad(500,1000)}a", ms, |x| x.fade);
+        assert_eq!([fade(0), fade(250), fade(500), fade(1000), fade(1500), fade(2000)], [255, 128, 0, 0, 128, 255]);
+        // Fade is added to the alpha of every color.
+        at("{\\fad(500,1000)\\3a&H80&}a", 250, |frame| {
+            let style = &frame.runs[0].style;
+            assert_eq!((style.primary_color.a, style.outline_color.a), (128, 191));
+        });
+    }
+
+    #[test]
+    fn fade() {
+        let fade = |ms: u64| at("{\\fade(255,0,128,0,500,1000,1500)\\fad(0,2000)}a", ms, |x| x.fade);
+        assert_eq!([fade(0), fade(250), fade(700), fade(1250), fade(1600)], [255, 128, 0, 64, 128]);
+    }
+
+    #[test]
+    fn transform() {
+        let size = |text: &str, ms: u64| at(text, ms, |x| x.runs[0].style.font_size);
+        assert_eq!(size("{\\t(\\fs40)}a", 1000), 30.0);
+        // Accel makes progress `((t - t1) / (t2 - t1)) ^ accel`.
+        assert_eq!(size("{\\t(0,1000,2,\\fs40)}a", 500), 25.0);
+        assert_eq!(size("{\\t(0,1000,0.5,\\fs40)}a", 250), 30.0);
+        assert_eq!(size("{\\t(2,\\fs40)}a", 1000), 25.0);
+        assert_eq!(size("{\\t(0,1000,2,\\fs40)}a", 1500), 40.0);
+        assert_eq!(size("{\\t(500,1000,\\fs40)}a", 250), 20.0);
+    }
+
+    #[test]
+    fn clips() {
+        let animated = "{\\clip(0,0,100,100)\\t(0,1000,\\clip(100,100,200,300))}a";
+        at(animated, 500, |x| assert_eq!(x.clip, Some(Clip::Rect { x1: 50.0, y1: 50.0, x2: 150.0, y2: 200.0 })));
+        at(animated, 1500, |x| assert_eq!(x.clip, Some(Clip::Rect { x1: 100.0, y1: 100.0, x2: 200.0, y2: 300.0 })));
+        at("{\\iclip(2,m 0 0 l 1 1)\\t(\\clip(0,0,1,1))}a", 500, |frame| {
+            assert_eq!(frame.clip, Some(Clip::Vector { scale: 2, commands: Cow::Borrowed("m 0 0 l 1 1") }));
+            assert!(frame.inverse_clip);
+        });
+    }
+
+    #[test]
+    fn karaoke_progress() {
+        at("{\\kf100}a", 250, |frame| assert_eq!(frame.karaoke_progress(&frame.runs[0]), Some(0.25)));
+    }
+}
@@ -0,0 +1,94 @@
+use std::borrow::Cow;
+
+/// Maximum length of encoded data line, as written by Aegisub.
+const LINE_LENGTH: usize = 80;
+
+/// File embedded into `[Fonts]` or `[Graphics]` section.
+///
+/// Data is kept encoded (ASS flavour of uuencode), use [`Attachment::decode`] to get bytes.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Attachment<'a> {
+    pub name: Cow<'a, str>,
+    pub data: Vec<Cow<'a, str>>,
+}
+
+impl<'a> Attachment<'a> {
+    /// Encodes file content.
+    pub fn encode(name: impl Into<Cow<'a, str>>, bytes: &[u8]) -> Self {
+        let mut encoded = String::with_capacity(bytes.len() * 4 / 3 + 4);
+        for chunk in bytes.chunks(3) {
+            let v = chunk.iter().enumerate().fold(0u32, |v, (i, b)| v | (*b as u32) << (16 - i * 8));
+            for i in 0..chunk.len() + 1 {
+                encoded.push((((v >> (18 - i * 6)) & 0x3f) as u8 + 33) as char);
+            }
+        }
+        let data = encoded
+            .as_bytes()
+            .chunks(LINE_LENGTH)
+            .map(|x| Cow::Owned(String::from_utf8_lossy(x).into_owned()))
+            .collect();
+        Self { name: name.into(), data }
+    }
+
+    pub fn decode(&self) -> Vec<u8> {
+        let chars: Vec<u32> = self
+            .data
+            .iter()
+            .flat_map(|x| x.bytes())
+            .filter(|x| (33..=96).contains(x))
+            .map(|x| (x - 33) as u32)
+            .collect();
+        let mut bytes = Vec::with_capacity(chars.len() * 3 / 4);
+        for group in chars.chunks(4) {
+            let v = group.iter().enumerate().fold(0u32, |v, (i, c)| v | c << (18 - i * 6));
+            for i in 0..group.len().saturating_sub(1) {
+                bytes.push((v >> (16 - i * 8)) as u8);
+            }
+        }
+        bytes
+    }
+}
+
+/// Parses raw lines of `[Fonts]` or `[Graphics]` section.
+/// `key` is `fontname` or `filename` respectively.
+pub fn parse_attachments<'a>(lines: &[Cow<'a, str>], key: &str) -> Vec<Attachment<'a>> {
+    let mut attachments: Vec<Attachment> = vec![];
+    for line in lines {
+        let name = match line {
+            Cow::Borrowed(line) => line.strip_prefix(key).and_then(|x| x.strip_prefix(": ")).map(Cow::Borrowed),
+            Cow::Owned(line) => line
+                .strip_prefix(key)
+                .and_then(|x| x.strip_prefix(": "))
+                .map(|x| Cow::Owned(x.to_string())),
+        };
+        match (name, attachments.last_mut()) {
+            (Some(name), _) => attachments.push(Attachment { name, data: vec![] }),
+            (None, Some(last)) => last.data.push(line.clone()),
+            (None, None) => {} // Data without a file name
+        }
+    }
+    attachments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let attachment = Attachment::encode("a.ttf", &bytes);
+        assert!(attachment.data.iter().all(|x| x.len() <= LINE_LENGTH));
+        assert_eq!(attachment.decode(), bytes);
+        assert_eq!(Attachment::encode("b", b"ab").data, vec!["97)"]);
+    }
+
+    #[test]
+    fn data_like_section_header() {
+        let data = "[Fonts]\nfontname: a.ttf\n[A!]\nB!C\n\n[Events]\n";
+        let ass = crate::parse_str(data).unwrap();
+        assert_eq!(ass.fonts.len(), 1);
+        assert_eq!(ass.fonts[0].name, "a.ttf");
+        assert_eq!(ass.fonts[0].data, vec!["[A!]", "B!C"]);
+    }
+}
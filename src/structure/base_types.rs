@@ -1,5 +1,8 @@
+use std::borrow::Cow;
 use std::convert::Infallible;
+use std::str::FromStr;
 use super::InvalidValue;
+use super::tags::format_number;
 
 macro_rules! gen_num {
     ($T: ty) => {
@@ -24,53 +27,104 @@ gen_num!(i64);
 gen_num!(f32);
 gen_num!(f64);
 
+/// Value of a field in a line, reverse of `TryFrom<LineField>`.
+pub trait ToLineField {
+    fn to_line_field(&self) -> String;
+}
+
+macro_rules! gen_int_field {
+    ($($T: ty),*) => {
+        $(impl ToLineField for $T {
+            fn to_line_field(&self) -> String {
+                self.to_string()
+            }
+        })*
+    };
+}
+
+gen_int_field!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl ToLineField for f32 {
+    fn to_line_field(&self) -> String {
+        format_number(*self as f64)
+    }
+}
+
+impl ToLineField for f64 {
+    fn to_line_field(&self) -> String {
+        format_number(*self)
+    }
+}
+
+impl ToLineField for bool {
+    fn to_line_field(&self) -> String {
+        if *self { "-1" } else { "0" }.to_string()
+    }
+}
+
+impl ToLineField for Color {
+    fn to_line_field(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ToLineField for &str {
+    fn to_line_field(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ToLineField for Cow<'_, str> {
+    fn to_line_field(&self) -> String {
+        self.to_string()
+    }
+}
+
 /// Color in ass file
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub struct Color { a: u8, b: u8, g: u8, r: u8 }
-impl<'a> TryFrom<LineField<'a>> for Color {
-    type Error = InvalidValue;
+pub struct Color { pub a: u8, pub b: u8, pub g: u8, pub r: u8 }
 
-    fn try_from(value: LineField<'a>) -> Result<Self, Self::Error> {
-        let value = value.value().strip_prefix("&H").ok_or(InvalidValue)?;
-        let value = value.strip_suffix("&").ok_or(InvalidValue)?;
+impl FromStr for Color {
+    type Err = InvalidValue;
+
+    /// Parses both `&HAABBGGRR` (styles) and `&HBBGGRR&` (override tags) forms.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim();
+        let value = value.strip_prefix("&H").or_else(|| value.strip_prefix("&h")).ok_or(InvalidValue)?;
+        let value = value.strip_suffix('&').unwrap_or(value);
+        let byte = |i: usize| u8::from_str_radix(&value[i..i + 2], 16);
         match value.len() {
-            6 => {
-                let b: &str = &value[0..2];
-                let g: &str = &value[2..4];
-                let r: &str = &value[4..6];
-                Ok(Color {
-                    a: 255,
-                    b: b.parse()?,
-                    g: g.parse()?,
-                    r: r.parse()?,
-                })
-            }
-            8 => {
-                let a: &str = &value[0..2];
-                let b: &str = &value[2..4];
-                let g: &str = &value[4..6];
-                let r: &str = &value[6..8];
-                Ok(Color {
-                    a: a.parse()?,
-                    b: b.parse()?,
-                    g: g.parse()?,
-                    r: r.parse()?,
-                })
-            }
+            6 => Ok(Color {
+                a: 0,
+                b: byte(0)?,
+                g: byte(2)?,
+                r: byte(4)?,
+            }),
+            8 => Ok(Color {
+                a: byte(0)?,
+                b: byte(2)?,
+                g: byte(4)?,
+                r: byte(6)?,
+            }),
             _ => Err(InvalidValue)
         }
     }
 }
 
+impl<'a> TryFrom<LineField<'a>> for Color {
+    type Error = InvalidValue;
+
+    fn try_from(value: LineField<'a>) -> Result<Self, Self::Error> {
+        value.value().parse()
+    }
+}
+
 impl<'a> TryFrom<LineField<'a>> for bool {
     type Error = InvalidValue;
 
     fn try_from(value: LineField<'a>) -> Result<Self, Self::Error> {
-        match value.value() {
-            "0" => Ok(false),
-            "-1" => Ok(true),
-            _ => Err(InvalidValue)
-        }
+        // VSFilter treats any non-zero value as true, though -1 is canonical.
+        Ok(value.value().parse::<i32>()? != 0)
     }
 }
 
@@ -91,10 +145,18 @@ impl<'a> LineField<'a> {
     }
 }
 
+// Kept as `TryFrom` for compatibility, `From` would replace this public impl with the blanket one.
+#[allow(clippy::infallible_try_from)]
 impl<'a> TryFrom<LineField<'a>> for &'a str {
     type Error = Infallible;
 
     fn try_from(value: LineField<'a>) -> Result<Self, Self::Error> {
         Ok(value.0)
     }
-}
\ No newline at end of file
+}
+
+impl<'a> From<LineField<'a>> for Cow<'a, str> {
+    fn from(value: LineField<'a>) -> Self {
+        Cow::Borrowed(value.0)
+    }
+}
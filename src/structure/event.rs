@@ -1,9 +1,9 @@
+use std::borrow::Cow;
 use std::num::ParseIntError;
-use std::str::FromStr;
 use super::base_types::*;
 use std::time::Duration;
-use asai_macro::{ FromLine };
-use crate::structure::{FromLine, InvalidValue};
+use asai_macro::{ AssEnum, FromLine, ToLine };
+use crate::structure::InvalidValue;
 
 impl<'a> TryFrom<LineField<'a>> for Duration {
     type Error = InvalidValue;
@@ -14,6 +14,9 @@ impl<'a> TryFrom<LineField<'a>> for Duration {
             .flat_map(|x| x.split('.'))
             .map(str::parse)
             .collect::<Result<Vec<u32>, ParseIntError>>()?;
+        if v.len() != 4 {
+            return Err(InvalidValue);
+        }
         let hours = v[0];
         let minutes = hours * 60 + v[1];
         let seconds = minutes * 60 + v[2];
@@ -22,50 +25,77 @@ impl<'a> TryFrom<LineField<'a>> for Duration {
     }
 }
 
-#[derive(FromLine, Debug, Copy, Clone, Eq, PartialEq)]
+impl ToLineField for Duration {
+    fn to_line_field(&self) -> String {
+        format_timestamp(*self)
+    }
+}
+
+/// Formats time as `H:MM:SS.CC`, rounding to centiseconds.
+pub fn format_timestamp(time: Duration) -> String {
+    let cs = (time.as_millis() + 5) / 10;
+    format!("{}:{:02}:{:02}.{:02}", cs / 360000, cs / 6000 % 60, cs / 100 % 60, cs % 100)
+}
+
+#[derive(FromLine, ToLine, Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Event<'a> {
     #[name("Layer")]
-    layer: u32,
+    pub layer: u32,
     #[name("Start")]
-    start: Duration,
+    pub start: Duration,
     #[name("End")]
-    end: Duration,
+    pub end: Duration,
     #[name("Style")]
-    #[default("Default")]
-    style: &'a str,
-    #[name("Actor")]
-    #[default("")]
-    actor: &'a str,
+    #[default(Cow::Borrowed("Default"))]
+    pub style: Cow<'a, str>,
+    #[name("Name")]
+    #[default(Cow::Borrowed(""))]
+    pub actor: Cow<'a, str>,
     #[name("MarginL")]
     #[default(0)]
-    margin_l: u32,
+    pub margin_l: u32,
     #[name("MarginR")]
     #[default(0)]
-    margin_r: u32,
+    pub margin_r: u32,
     #[name("MarginV")]
     #[default(0)]
-    margin_v: u32,
+    pub margin_v: u32,
     #[name("Effect")]
-    #[default("")]
-    effect: &'a str,
+    #[default(Cow::Borrowed(""))]
+    pub effect: Cow<'a, str>,
     #[name("Text")]
-    text: &'a str,
+    pub text: Cow<'a, str>,
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+impl Event<'_> {
+    /// Copies borrowed fields, so the event doesn't depend on the script text.
+    pub fn into_owned(self) -> Event<'static> {
+        Event {
+            layer: self.layer,
+            start: self.start,
+            end: self.end,
+            style: Cow::Owned(self.style.into_owned()),
+            actor: Cow::Owned(self.actor.into_owned()),
+            margin_l: self.margin_l,
+            margin_r: self.margin_r,
+            margin_v: self.margin_v,
+            effect: Cow::Owned(self.effect.into_owned()),
+            text: Cow::Owned(self.text.into_owned()),
+        }
+    }
+}
+
+#[derive(AssEnum, Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum EventKey {
     Comment,
     Dialogue,
 }
 
-impl FromStr for EventKey {
-    type Err = InvalidValue;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "Comment" => Ok(Self::Comment),
-            "Dialogue" => Ok(Self::Dialogue),
-            _ => Err(InvalidValue)
+impl EventKey {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Comment => "Comment",
+            Self::Dialogue => "Dialogue",
         }
     }
 }
@@ -0,0 +1,207 @@
+use std::fmt::{Display, Formatter};
+use crate::structure::{EVENT_FORMAT, STYLE_FORMAT};
+
+/// Columns of a `Format:` line, in their order.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Format<'a> {
+    value: &'a str,
+    columns: Vec<&'a str>,
+}
+
+/// Section with a format asai knows.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FormatKind {
+    Styles,
+    Events,
+}
+
+impl FormatKind {
+    /// Standard format of the section, also used when it has no `Format` line.
+    pub fn format(self) -> Format<'static> {
+        match self {
+            Self::Styles => Format::parse(STYLE_FORMAT),
+            Self::Events => Format::parse(EVENT_FORMAT),
+        }
+    }
+
+    /// Columns [`Style`](crate::structure::style::Style) and [`Event`](crate::structure::event::Event)
+    /// can't be parsed without. Others get default values.
+    pub fn mandatory(self) -> Vec<&'static str> {
+        match self {
+            Self::Styles => self.format().columns.into_iter().filter(|x| *x != "Encoding").collect(),
+            Self::Events => vec!["Layer", "Start", "End", "Text"],
+        }
+    }
+}
+
+/// Order of columns when writing a section.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum ColumnOrder {
+    /// Standard format of the section.
+    #[default]
+    Canonical,
+    /// `Format` line the section was parsed with.
+    Preserved,
+}
+
+/// Problem of a `Format:` line.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FormatDiagnostic<'a> {
+    /// Column appears more than once. The last one is used, by derived types and
+    /// [`Record`](crate::structure::record::Record) alike.
+    Duplicate(&'a str),
+    Missing(&'static str),
+    /// `Text` is not the last event column, so commas in text shift the columns after it.
+    TextNotLast,
+    /// Unknown column that looks like a known one, like `OutlineColor` for `OutlineColour`.
+    Misspelled { column: &'a str, expected: &'static str },
+    /// Column the section doesn't have. It is ignored.
+    Unknown(&'a str),
+}
+
+impl Display for FormatDiagnostic<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Duplicate(column) => write!(f, "duplicate column {:?}", column),
+            Self::Missing(column) => write!(f, "missing column {:?}", column),
+            Self::TextNotLast => write!(f, "column \"Text\" is not the last one"),
+            Self::Misspelled { column, expected } => write!(f, "unknown column {:?}, did you mean {:?}?", column, expected),
+            Self::Unknown(column) => write!(f, "unknown column {:?}", column),
+        }
+    }
+}
+
+/// Whether an unknown column is likely a typo of a known one: same name ignoring case, spaces and
+/// `Color`/`Colour`, or one edit away.
+fn misspelled(column: &str, known: &str) -> bool {
+    let normalize = |s: &str| s.to_lowercase().replace(' ', "").replace("color", "colour");
+    let (column, known) = (normalize(column), normalize(known));
+    column == known || known.len() >= 3 && edit_distance(&column, &known) <= 1
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(x != *y);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+impl<'a> Format<'a> {
+    pub fn parse(value: &'a str) -> Self {
+        Self { value, columns: value.split(',').map(str::trim).collect() }
+    }
+
+    /// Format as written in the script.
+    pub fn as_str(&self) -> &'a str {
+        self.value
+    }
+
+    pub fn columns(&self) -> &[&'a str] {
+        &self.columns
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    pub fn contains(&self, column: &str) -> bool {
+        self.columns.contains(&column)
+    }
+
+    /// Duplicate columns and columns that look like misspelled `known` columns.
+    /// Other columns are not reported, as types can keep them in `#[rest]` fields.
+    pub fn check_columns(&self, known: &[&'static str]) -> Vec<FormatDiagnostic<'a>> {
+        let mut diagnostics = vec![];
+        for (i, column) in self.columns.iter().enumerate() {
+            let before = self.columns[..i].iter().filter(|x| *x == column).count();
+            if before > 0 {
+                // Reported once, on the second occurrence.
+                if before == 1 {
+                    diagnostics.push(FormatDiagnostic::Duplicate(column));
+                }
+                continue;
+            }
+            if known.contains(column) {
+                continue;
+            }
+            if let Some(expected) = known.iter().find(|x| !self.contains(x) && misspelled(column, x)) {
+                diagnostics.push(FormatDiagnostic::Misspelled { column, expected });
+            }
+        }
+        diagnostics
+    }
+
+    /// All problems of the format for a section asai knows.
+    pub fn check(&self, kind: FormatKind) -> Vec<FormatDiagnostic<'a>> {
+        let known = kind.format().columns;
+        let mut diagnostics = self.check_columns(&known);
+        for column in &self.columns {
+            let reported = diagnostics.iter().any(|x| matches!(x, FormatDiagnostic::Misspelled { column: c, .. } if c == column));
+            if !known.contains(column) && !reported && !diagnostics.contains(&FormatDiagnostic::Unknown(column)) {
+                diagnostics.push(FormatDiagnostic::Unknown(column));
+            }
+        }
+        for column in kind.mandatory() {
+            let misspelled = diagnostics.iter().any(|x| matches!(x, FormatDiagnostic::Misspelled { expected, .. } if *expected == column));
+            if !self.contains(column) && !misspelled {
+                diagnostics.push(FormatDiagnostic::Missing(column));
+            }
+        }
+        if kind == FormatKind::Events && self.contains("Text") && self.columns.last() != Some(&"Text") {
+            diagnostics.push(FormatDiagnostic::TextNotLast);
+        }
+        diagnostics
+    }
+}
+
+impl Display for Format<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.columns.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check() {
+        assert!(FormatKind::Events.format().check(FormatKind::Events).is_empty());
+        assert!(FormatKind::Styles.format().check(FormatKind::Styles).is_empty());
+        let format = Format::parse("Layer, Start, Text, Start, OutlineColor, Foo");
+        assert_eq!(
+            format.check(FormatKind::Events),
+            vec![
+                FormatDiagnostic::Duplicate("Start"),
+                FormatDiagnostic::Unknown("OutlineColor"),
+                FormatDiagnostic::Unknown("Foo"),
+                FormatDiagnostic::Missing("End"),
+                FormatDiagnostic::TextNotLast,
+            ]
+        );
+        let format = Format::parse("Name, Fontname, OutlineColor");
+        assert!(format.check(FormatKind::Styles).contains(&FormatDiagnostic::Misspelled { column: "OutlineColor", expected: "OutlineColour" }));
+        assert!(FormatKind::Styles.mandatory().contains(&"OutlineColour"));
+        assert!(!FormatKind::Styles.mandatory().contains(&"Encoding"));
+    }
+
+    #[test]
+    fn misspelled() {
+        assert!(super::misspelled("outline colour", "OutlineColour"));
+        assert!(super::misspelled("Strt", "Start"));
+        assert!(!super::misspelled("Foo", "Start"));
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
@@ -1,4 +1,5 @@
-use crate::structure::{FromLine, FromLines, InvalidValue};
+use crate::structure::format::{Format, FormatDiagnostic};
+use crate::structure::{ColumnPlan, FromLine, FromLines};
 use std::marker::PhantomData;
 use std::ops::Index;
 use std::str::FromStr;
@@ -9,6 +10,8 @@ where
 {
     pd: PhantomData<&'a str>,
     lines: Vec<(K, L)>,
+    format: Format<'a>,
+    diagnostics: Vec<FormatDiagnostic<'a>>,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -24,8 +27,10 @@ impl<'a, K: FromStr, L: FromLine<'a>> FromLines<'a> for FormattedSection<'a, K,
         let format = lines
             .iter()
             .find(|x| x.0 == "Format")
-            .ok_or(FormattedSectionParseError::FormatNotFound)?
-            .1;
+            .map(|x| Format::parse(x.1))
+            .ok_or(FormattedSectionParseError::FormatNotFound)?;
+        let diagnostics = format.check_columns(L::column_names());
+        let plan = ColumnPlan::new(format.as_str());
         let mut lines_ = Vec::with_capacity(lines.len());
         for &line in lines {
             if line.0 == "Format" {
@@ -35,13 +40,15 @@ impl<'a, K: FromStr, L: FromLine<'a>> FromLines<'a> for FormattedSection<'a, K,
                 line.0
                     .parse::<K>()
                     .map_err(|_| FormattedSectionParseError::InvalidLine(line.0, line.1))?,
-                L::from_line(line.1, format)
+                L::from_planned_line(line.1, &plan)
                     .map_err(|_| FormattedSectionParseError::InvalidLine(line.0, line.1))?,
             ));
         }
         Ok(Self {
-            pd: PhantomData::default(),
+            pd: PhantomData,
             lines: lines_,
+            format,
+            diagnostics,
         })
     }
 }
@@ -50,6 +57,23 @@ impl<'a, K: FromStr, L: FromLine<'a>> FormattedSection<'a, K, L> {
     pub fn len(&self) -> usize {
         self.lines.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(K, L)> {
+        self.lines.iter()
+    }
+
+    pub fn format(&self) -> &Format<'a> {
+        &self.format
+    }
+
+    /// Duplicate and misspelled columns of the `Format` line, compared to [`FromLine::column_names`].
+    pub fn diagnostics(&self) -> &[FormatDiagnostic<'a>] {
+        &self.diagnostics
+    }
 }
 
 impl<'a, K: FromStr, L: FromLine<'a>> Index<usize> for FormattedSection<'a, K, L> {
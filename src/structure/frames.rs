@@ -0,0 +1,67 @@
+use crate::structure::Events;
+use crate::video::FrameTimes;
+
+impl Events<'_> {
+    /// Moves start and end of every event to the middle between frame timestamps, keeping frames
+    /// the event is shown on, so the result doesn't depend on how a player rounds times.
+    ///
+    /// Returns indices of events shown on no frame at all. They are left with zero duration.
+    pub fn snap_to_frames(&mut self, frames: &FrameTimes) -> Vec<usize> {
+        let mut empty = vec![];
+        for (i, (_, event)) in self.events.iter_mut().enumerate() {
+            let first = frames.start_frame(event.start);
+            let last = frames.end_frame(event.end);
+            event.start = frames.start_time(first);
+            if last < first {
+                event.end = event.start;
+                empty.push(i);
+            } else {
+                event.end = frames.end_time(last);
+            }
+        }
+        empty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+
+    fn events(times: &[(u64, u64)]) -> Events<'static> {
+        let mut data = "[Events]\n".to_string();
+        for (start, end) in times {
+            let time = |ms: u64| crate::structure::event::format_timestamp(Duration::from_millis(ms));
+            data.push_str(&format!("Dialogue: 0,{},{},Default,,0,0,0,,a\n", time(*start), time(*end)));
+        }
+        let ass = crate::parse_str(&data).unwrap();
+        let events = ass.events.events.into_iter().map(|(k, e)| (k, e.into_owned())).collect();
+        Events { events, ..Default::default() }
+    }
+
+    fn times(events: &Events) -> Vec<(u128, u128)> {
+        events.events.iter().map(|(_, e)| (e.start.as_millis(), e.end.as_millis())).collect()
+    }
+
+    #[test]
+    fn constant() {
+        let frames = FrameTimes::constant(24000.0 / 1001.0).unwrap();
+        let mut events = events(&[(1000, 2000), (0, 50), (1010, 1020)]);
+        assert_eq!(events.snap_to_frames(&frames), vec![2]);
+        // Frames 24 to 47: the middles between frame timestamps, rounded to centiseconds.
+        assert_eq!(times(&events), vec![(980, 1980), (0, 60), (1020, 1020)]);
+        // Snapped times are whole centiseconds, so writing them keeps the frames.
+        let snapped = times(&events);
+        assert_eq!(events.snap_to_frames(&frames), vec![2]);
+        assert_eq!(times(&events), snapped);
+    }
+
+    #[test]
+    fn timecodes() {
+        let frames = FrameTimes::parse_timecodes("# timestamp format v2\n0\n40\n100\n140\n").unwrap();
+        let mut events = events(&[(50, 120), (50, 90), (100, 300)]);
+        assert_eq!(events.snap_to_frames(&frames), vec![1]);
+        // Frame 2 is from 100 to 140 ms, frames after the last timestamp are extrapolated.
+        assert_eq!(times(&events), vec![(70, 120), (70, 70), (70, 300)]);
+    }
+}
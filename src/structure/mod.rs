@@ -4,22 +4,91 @@ pub mod base_types;
 pub mod event;
 pub mod style;
 pub mod formatted_section;
+pub mod format;
+pub mod record;
+pub mod attachment;
+pub mod write;
+pub mod srt;
+pub mod tags;
+pub mod resample;
+pub mod state;
+pub mod animation;
+pub mod plain_text;
+pub mod search;
+pub mod optimize;
+pub mod timing;
+pub mod frames;
 
 use std::convert::Infallible;
-use crate::iter::{Element, Elements};
-use crate::structure::event::Event;
+use asai_macro::AssEnum;
+use crate::iter::{Element, Elements, InvalidElement};
+use crate::structure::attachment::{parse_attachments, Attachment};
+use std::borrow::Cow;
+use crate::structure::event::{Event, EventKey};
+use crate::structure::style::Style;
+use crate::structure::format::{Format, FormatDiagnostic, FormatKind};
 use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
 use std::marker::PhantomData;
 use std::str::FromStr;
+use std::time::Duration;
 
 pub struct Ass<'a> {
     pub info: ScriptInfo<'a>,
     pub styles: Styles<'a>,
     pub events: Events<'a>,
+    pub fonts: Vec<Attachment<'a>>,
+    pub graphics: Vec<Attachment<'a>>,
+    /// Sections asai doesn't parse, like `[Aegisub Project Garbage]`. They are written back unchanged.
+    pub other_sections: Vec<RawSection<'a>>,
+}
+
+/// Section kept as text.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RawSection<'a> {
+    pub name: &'a str,
+    /// Lines after the section header, comments included.
+    pub lines: Vec<Cow<'a, str>>,
+}
+
+/// Line of `[V4+ Styles]` or `[Events]` that couldn't be parsed, like an event with invalid time
+/// or a `Picture:` line. It is written back unchanged.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UnparsedLine<'a> {
+    /// Number of parsed items before the line.
+    pub position: usize,
+    pub text: Cow<'a, str>,
+}
+
+/// Line as it was written in the script.
+fn raw_line<'a>(element: &Result<Element<'a>, InvalidElement<'a>>) -> Cow<'a, str> {
+    match element {
+        Ok(Element::SectionDefinition(name)) => Cow::Owned(format!("[{}]", name)),
+        Ok(Element::Line { name, value }) => Cow::Owned(format!("{}: {}", name, value)),
+        Ok(Element::Comment(comment)) => Cow::Owned(format!(";{}", comment)),
+        Err(InvalidElement(line)) => Cow::Borrowed(line),
+    }
 }
 
 
 
+/// Format used for `[V4+ Styles]` when section has no `Format` line.
+pub const STYLE_FORMAT: &str = "Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding";
+/// Format used for `[Events]` when section has no `Format` line.
+pub const EVENT_FORMAT: &str = "Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text";
+
+/// Sections of scripts written by common tools.
+pub const KNOWN_SECTIONS: &[&str] = &[
+    "Script Info",
+    "V4+ Styles",
+    "V4 Styles",
+    "Events",
+    "Fonts",
+    "Graphics",
+    "Aegisub Project Garbage",
+    "Aegisub Extradata",
+];
+
 pub trait FromLines<'a> where Self: Sized {
     type Err;
     fn from_lines(lines: &[(&'a str, &'a str)]) -> Result<Self, Self::Err>;
@@ -31,49 +100,80 @@ pub enum SectionParseError {
     CannotParseSection,
 }
 
+impl Display for SectionParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SectionNotFound => write!(f, "section not found"),
+            Self::CannotParseSection => write!(f, "cannot parse section"),
+        }
+    }
+}
+
+impl Error for SectionParseError {}
+
 impl<'a> Ass<'a> {
-    pub fn from_elements<T: Iterator<Item = &'a str>>(iter: Elements<'a, T>) -> Self {
-        let mut info: ScriptInfo = Default::default();
-        let mut styles: Styles = Default::default();
-        let mut events: Option<Events> = Default::default();
+    /// Fails with [`SectionParseError::SectionNotFound`] if there is no `[Events]` section.
+    pub fn from_elements<T: Iterator<Item = &'a str>>(iter: Elements<'a, T>) -> Result<Self, SectionParseError> {
+        let mut ass = Self {
+            info: Default::default(),
+            styles: Default::default(),
+            events: Default::default(),
+            fonts: vec![],
+            graphics: vec![],
+            other_sections: vec![],
+        };
+        let mut has_events = false;
         let mut current_section: Option<&'a str> = Default::default();
-        let mut lines: Vec<(&'a str, &'a str)> = vec![];
+        let mut elements: Vec<Result<Element<'a>, InvalidElement<'a>>> = vec![];
         for i in iter {
-            if i.is_err() {
-                continue;
-            }
-            match i.unwrap() {
-                Element::SectionDefinition(name) => {
-                    if let Some(name) = current_section {
-                        match name {
-                            "Script Info" => info = ScriptInfo::from_lines(&lines).unwrap(),
-                            "V4+ Styles" => styles = Styles::from_lines(&lines).unwrap(),
-                            "Events" => events = Some(Events::from_lines(&lines).unwrap()),
-                            _ => (),
-                        }
+            let attachments = matches!(current_section, Some("Fonts" | "Graphics"));
+            match i {
+                // Uuencoded data can look like `[...]`, so only known sections end attachments.
+                Ok(Element::SectionDefinition(name)) if !attachments || KNOWN_SECTIONS.contains(&name) => {
+                    if let Some(section) = current_section {
+                        has_events |= ass.finish_section(section, &elements);
                     }
                     current_section = Some(name);
-                    lines.clear();
+                    elements.clear();
                 }
-                Element::Line { name, value } if name != "!" => lines.push((name, value)),
-                _ => (), // Ignore comments
+                element => elements.push(element),
             }
         }
 
-		if let Some(current_section) = current_section {
-			match current_section {
-				"Script Info" => info = ScriptInfo::from_lines(&lines).unwrap(),
-				"V4+ Styles" => styles = Styles::from_lines(&lines).unwrap(),
-				"Events" => events = Some(Events::from_lines(&lines).unwrap()),
-				_ => (),
-			}
-		}
-
-        Self {
-            info,
-            styles,
-            events: events.unwrap(),
+        if let Some(section) = current_section {
+            has_events |= ass.finish_section(section, &elements);
+        }
+        if !has_events {
+            return Err(SectionParseError::SectionNotFound);
+        }
+        Ok(ass)
+    }
+
+    /// Parses collected section, returns whether it was `[Events]`.
+    fn finish_section(&mut self, name: &'a str, elements: &[Result<Element<'a>, InvalidElement<'a>>]) -> bool {
+        // Attachments and unknown sections are not key-value lines, so they are kept as is.
+        let raw = || elements.iter().map(raw_line).collect::<Vec<_>>();
+        match name {
+            "Script Info" => {
+                let lines: Vec<(&'a str, &'a str)> = elements
+                    .iter()
+                    .filter_map(|x| match x {
+                        Ok(Element::Line { name, value }) if *name != "!" => Some((*name, *value)),
+                        _ => None, // Ignore comments
+                    })
+                    .collect();
+                self.info = ScriptInfo::from_lines(&lines).unwrap();
+            }
+            "V4+ Styles" => self.styles = Styles::parse(elements),
+            "Events" => {
+                self.events = Events::parse(elements);
+                return true;
+            }
+            "Fonts" => self.fonts = parse_attachments(&raw(), "fontname"),
+            "Graphics" => self.graphics = parse_attachments(&raw(), "filename"),
+            _ => self.other_sections.push(RawSection { name, lines: raw() }),
         }
+        false
     }
 
     pub fn parse_section<'b, T: FromLines<'b>>(name: &str, s: &'b str) -> Result<T, SectionParseError> {
@@ -108,6 +208,9 @@ impl<'a> Ass<'a> {
     }
 }
 
+/// Credits from `[Script Info]` (`Original Script`, `Script Updated By`, etc.).
+///
+/// Raw values are kept as written. Use `*_authors` methods to get separate names.
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Authors<'a> {
     script: Option<&'a str>,
@@ -118,7 +221,86 @@ pub struct Authors<'a> {
     update_details: Option<&'a str>,
 }
 
-#[derive(Debug, Copy, Clone, Default)]
+impl<'a> Authors<'a> {
+    /// Splits list of authors like `Foo, Bar & Baz` into separate names.
+    pub fn split(value: &'a str) -> impl Iterator<Item = &'a str> {
+        value
+            .split([',', '&', '/', ';'])
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+    }
+
+    /// Sets field by its `[Script Info]` key. Returns `false` if key is not an authors key.
+    pub fn set(&mut self, key: &str, value: &'a str) -> bool {
+        let field = match key {
+            "Original Script" => &mut self.script,
+            "Original Translation" => &mut self.translation,
+            "Original Editing" => &mut self.editing,
+            "Original Timing" => &mut self.timing,
+            "Script Updated By" => &mut self.updated_by,
+            "Update Details" => &mut self.update_details,
+            _ => return false,
+        };
+        *field = Some(value);
+        true
+    }
+
+    pub fn script(&self) -> Option<&'a str> {
+        self.script
+    }
+
+    pub fn translation(&self) -> Option<&'a str> {
+        self.translation
+    }
+
+    pub fn editing(&self) -> Option<&'a str> {
+        self.editing
+    }
+
+    pub fn timing(&self) -> Option<&'a str> {
+        self.timing
+    }
+
+    pub fn updated_by(&self) -> Option<&'a str> {
+        self.updated_by
+    }
+
+    /// Free-form text, so there is no `*_authors` variant for it.
+    pub fn update_details(&self) -> Option<&'a str> {
+        self.update_details
+    }
+
+    pub fn script_authors(&self) -> impl Iterator<Item = &'a str> {
+        self.script.into_iter().flat_map(Self::split)
+    }
+
+    pub fn translation_authors(&self) -> impl Iterator<Item = &'a str> {
+        self.translation.into_iter().flat_map(Self::split)
+    }
+
+    pub fn editing_authors(&self) -> impl Iterator<Item = &'a str> {
+        self.editing.into_iter().flat_map(Self::split)
+    }
+
+    pub fn timing_authors(&self) -> impl Iterator<Item = &'a str> {
+        self.timing.into_iter().flat_map(Self::split)
+    }
+
+    pub fn updated_by_authors(&self) -> impl Iterator<Item = &'a str> {
+        self.updated_by.into_iter().flat_map(Self::split)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.script.is_none()
+            && self.translation.is_none()
+            && self.editing.is_none()
+            && self.timing.is_none()
+            && self.updated_by.is_none()
+            && self.update_details.is_none()
+    }
+}
+
+#[derive(AssEnum, Debug, Copy, Clone, Default)]
 pub enum ScriptCollisionsType {
     #[default]
     Normal,
@@ -175,20 +357,42 @@ impl FromStr for TimeScale {
 
 #[derive(Debug, Copy, Clone, Default)]
 pub struct Resolution {
-    x: Option<u64>,
-    y: Option<u64>,
+    pub x: Option<u64>,
+    pub y: Option<u64>,
 }
 
-#[derive(Debug, Copy, Clone, Default)]
+impl Resolution {
+    pub fn new(x: u64, y: u64) -> Self {
+        Self { x: Some(x), y: Some(y) }
+    }
+
+    /// Resolution renderers actually use, filling missing `PlayResX`/`PlayResY` like libass does.
+    pub fn resolved(&self) -> (u64, u64) {
+        match (self.x, self.y) {
+            (Some(x), Some(y)) => (x, y),
+            (Some(1280), None) => (1280, 1024),
+            (Some(x), None) => (x, x * 3 / 4),
+            (None, Some(1024)) => (1280, 1024),
+            (None, Some(y)) => (y * 4 / 3, y),
+            (None, None) => (384, 288),
+        }
+    }
+}
+
+#[derive(AssEnum, Debug, Copy, Clone, Default)]
 pub enum WrapStyle {
     #[default]
+    #[value(0)]
     SmartConstant = 0,
+    #[value(1)]
     EndOfLine = 1,
+    #[value(2)]
     NoWrap = 2,
+    #[value(3)]
     SmartVariable = 3,
 }
 
-#[derive(Debug, Copy, Clone, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct ScriptInfo<'a> {
     pub resolution: Resolution,
     pub authors: Authors<'a>,
@@ -199,51 +403,69 @@ pub struct ScriptInfo<'a> {
     pub color_depth: ColorDepth,
     pub collisions: ScriptCollisionsType,
     pub wrap_style: WrapStyle,
+    /// Fields not known to asai, like `ScaledBorderAndShadow`, in order of appearance.
+    pub other: Vec<(&'a str, &'a str)>,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Styles<'a> {
-    pd: PhantomData<&'a str>,
+    pub styles: Vec<Style<'a>>,
+    /// `Format` line of the section, `None` if it had none.
+    pub format: Option<Format<'a>>,
+    /// Problems of the `Format` line.
+    pub diagnostics: Vec<FormatDiagnostic<'a>>,
+    /// Lines other than styles, in order.
+    pub unparsed: Vec<UnparsedLine<'a>>,
 }
 
-#[derive(Debug, Clone, Default)]
-pub struct Events<'a> {
-    pub events: Vec<Event<'a>>,
-}
+impl<'a> Styles<'a> {
+    pub fn get(&self, name: &str) -> Option<&Style<'a>> {
+        self.styles.iter().find(|x| x.name == name)
+    }
 
-#[derive(Debug)]
-pub struct InvalidValue;
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Style<'a>> {
+        self.styles.iter_mut().find(|x| x.name == name)
+    }
 
-impl<E: Error> From<E> for InvalidValue {
-    fn from(_value: E) -> Self {
-        InvalidValue
+    /// Style used for rendering an event with style `name`. Falls back to `Default` like renderers do.
+    pub fn resolve(&self, name: &str) -> Option<&Style<'a>> {
+        self.get(name).or_else(|| self.get("Default"))
     }
 }
 
-impl FromStr for ScriptCollisionsType {
-    type Err = InvalidValue;
+#[derive(Debug, Clone, Default)]
+pub struct Events<'a> {
+    pub events: Vec<(EventKey, Event<'a>)>,
+    /// `Format` line of the section, `None` if it had none.
+    pub format: Option<Format<'a>>,
+    /// Problems of the `Format` line.
+    pub diagnostics: Vec<FormatDiagnostic<'a>>,
+    /// Lines other than events, in order.
+    pub unparsed: Vec<UnparsedLine<'a>>,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "Normal" => Ok(Self::Normal),
-            "Reverse" => Ok(Self::Reverse),
-            _ => Err(InvalidValue),
+impl<'a> Events<'a> {
+    /// Moves all events by `millis` milliseconds. Times are clamped to zero.
+    pub fn shift(&mut self, millis: i64) {
+        let by = Duration::from_millis(millis.unsigned_abs());
+        for (_, event) in &mut self.events {
+            if millis >= 0 {
+                event.start += by;
+                event.end += by;
+            } else {
+                event.start = event.start.saturating_sub(by);
+                event.end = event.end.saturating_sub(by);
+            }
         }
     }
 }
 
-impl FromStr for WrapStyle {
-    type Err = InvalidValue;
+#[derive(Debug)]
+pub struct InvalidValue;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let desc: u8 = s.parse()?;
-        match desc {
-            0 => Ok(Self::SmartConstant),
-            1 => Ok(Self::EndOfLine),
-            2 => Ok(Self::NoWrap),
-            3 => Ok(Self::SmartVariable),
-            _ => Err(InvalidValue),
-        }
+impl<E: Error> From<E> for InvalidValue {
+    fn from(_value: E) -> Self {
+        InvalidValue
     }
 }
 
@@ -255,18 +477,16 @@ impl<'a> FromLines<'a> for ScriptInfo<'a> {
         for i in lines {
             match i.0 {
                 "Title" => info.title = Some(i.1),
-                i if i.starts_with("Original") || i.contains("Update") => {
-                    println!("INFO: Authors info parsing is not implemented yet. Field {:?} will be ignored.", i)
-                } // Ignore it for now.
+                key if info.authors.set(key, i.1) => {}
                 "Sync Point" => info.sync_point = Some(i.1), // Never seen this field, idk what format of this field is.
-                "Script Type" => info.version = i.1,
+                "ScriptType" | "Script Type" => info.version = i.1,
                 "Timer" => info.timescale = i.1.parse().unwrap_or_default(),
                 "Collisions" => info.collisions = i.1.parse().unwrap_or_default(),
                 "PlayDepth" => info.color_depth = i.1.parse().unwrap_or_default(),
                 "PlayResY" => info.resolution.y = i.1.parse().ok(),
                 "PlayResX" => info.resolution.x = i.1.parse().ok(),
                 "WrapStyle" => info.wrap_style = i.1.parse().unwrap_or_default(),
-                _ => {} // For unknown fields
+                _ => info.other.push(*i),
             }
         }
 
@@ -279,11 +499,70 @@ impl<'a> FromLines<'a> for ScriptInfo<'a> {
     }
 }
 
+/// Index and value of the first `Format` line.
+fn find_format<'a>(elements: &[Result<Element<'a>, InvalidElement<'a>>]) -> Option<(usize, Format<'a>)> {
+    elements.iter().enumerate().find_map(|(i, x)| match x {
+        Ok(Element::Line { name: "Format", value }) => Some((i, Format::parse(value))),
+        _ => None,
+    })
+}
+
+fn as_elements<'a>(lines: &[(&'a str, &'a str)]) -> Vec<Result<Element<'a>, InvalidElement<'a>>> {
+    lines.iter().map(|(name, value)| Ok(Element::Line { name, value })).collect()
+}
+
+impl<'a> Styles<'a> {
+    fn parse(elements: &[Result<Element<'a>, InvalidElement<'a>>]) -> Self {
+        let (format_index, format) = find_format(elements).unzip();
+        let diagnostics = format.as_ref().map(|x| x.check(FormatKind::Styles)).unwrap_or_default();
+        let columns = format.as_ref().map_or(STYLE_FORMAT, Format::as_str);
+        let plan = ColumnPlan::new(columns);
+        let mut section = Self { format, diagnostics, ..Default::default() };
+        for (i, element) in elements.iter().enumerate() {
+            if Some(i) == format_index {
+                continue;
+            }
+            if let Ok(Element::Line { name: "Style", value }) = element {
+                if let Ok(style) = Style::from_planned_line(value, &plan) {
+                    section.styles.push(style);
+                    continue;
+                }
+            }
+            section.unparsed.push(UnparsedLine { position: section.styles.len(), text: raw_line(element) });
+        }
+        section
+    }
+}
+
 impl<'a> FromLines<'a> for Styles<'a> {
-    type Err = ();
+    type Err = Infallible;
 
-    fn from_lines(_lines: &[(&'a str, &'a str)]) -> Result<Self, Self::Err> {
-        Ok(Self::default())
+    fn from_lines(lines: &[(&'a str, &'a str)]) -> Result<Self, Self::Err> {
+        Ok(Self::parse(&as_elements(lines)))
+    }
+}
+
+impl<'a> Events<'a> {
+    fn parse(elements: &[Result<Element<'a>, InvalidElement<'a>>]) -> Self {
+        let (format_index, format) = find_format(elements).unzip();
+        let diagnostics = format.as_ref().map(|x| x.check(FormatKind::Events)).unwrap_or_default();
+        let columns = format.as_ref().map_or(EVENT_FORMAT, Format::as_str);
+        let plan = ColumnPlan::new(columns);
+        let mut section = Self { format, diagnostics, ..Default::default() };
+        for (i, element) in elements.iter().enumerate() {
+            if Some(i) == format_index {
+                continue;
+            }
+            // Lines with other keys (`Picture`, `Sound`, etc.) are not supported and kept as is.
+            if let Ok(Element::Line { name, value }) = element {
+                if let (Ok(key), Ok(event)) = (name.parse(), Event::from_planned_line(value, &plan)) {
+                    section.events.push((key, event));
+                    continue;
+                }
+            }
+            section.unparsed.push(UnparsedLine { position: section.events.len(), text: raw_line(element) });
+        }
+        section
     }
 }
 
@@ -291,30 +570,7 @@ impl<'a> FromLines<'a> for Events<'a> {
     type Err = Infallible;
 
     fn from_lines(lines: &[(&'a str, &'a str)]) -> Result<Self, Self::Err> {
-		// println!("Here");
-        let format = lines
-            .iter()
-            .find(|x| x.0 == "Format")
-            .map(|x| x.1)
-            .or_else(|| {
-                Some("Layer, Start, End, Style, Actor, MarginL, MarginR, MarginV, Effect, Text")
-            })
-            .unwrap();
-		// println!("{}", format);
-		// let events = vec![];
-		// for (name, value) in lines {
-		// 	if name == &"Format" {
-		// 		continue;
-		// 	}
-		// 	let ev = Event::from_line(value, format);
-		// }
-        let events: Vec<Event> = lines
-            .iter()
-            .filter(|(name, value)| *name != "Format")
-            .map(|x| FromLine::from_line(x.1, format))
-            .filter_map(|x| x.ok())
-            .collect();
-        Ok(Self { events })
+        Ok(Self::parse(&as_elements(lines)))
     }
 }
 
@@ -322,5 +578,435 @@ pub trait FromLine<'a>
 where
     Self: Sized,
 {
-    fn from_line(item: &'a str, format: &str) -> Result<Self, InvalidValue>;
+    fn from_line(item: &'a str, format: &'a str) -> Result<Self, InvalidValue>;
+
+    /// Columns with a field, in the order [`ColumnPlan`] refers to them. Empty for types matching columns by name.
+    fn column_names() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Like [`FromLine::from_line`], with columns already matched to fields by `plan`.
+    fn from_planned_line(item: &'a str, plan: &ColumnPlan<'a, Self>) -> Result<Self, InvalidValue> {
+        Self::from_line(item, plan.format())
+    }
+}
+
+/// `Format:` line with every column matched to a field of `T`, as index into [`FromLine::column_names`].
+/// Built once per section and reused for all of its lines.
+pub struct ColumnPlan<'a, T> {
+    format: &'a str,
+    fields: Cow<'a, [Option<usize>]>,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<'a, T: FromLine<'a>> ColumnPlan<'a, T> {
+    pub fn new(format: &'a str) -> Self {
+        let names = T::column_names();
+        let fields = format
+            .split(',')
+            .map(str::trim)
+            .map(|column| names.iter().position(|x| *x == column))
+            .collect();
+        Self { format, fields: Cow::Owned(fields), _type: PhantomData }
+    }
+}
+
+impl<'a, T> ColumnPlan<'a, T> {
+    /// Plan with `fields` of a plan built from `format`, for callers that keep only the fields
+    /// because they can't keep `format` borrowed.
+    pub(crate) fn borrowed(format: &'a str, fields: &'a [Option<usize>]) -> Self {
+        debug_assert_eq!(format.split(',').count(), fields.len());
+        Self { format, fields: Cow::Borrowed(fields), _type: PhantomData }
+    }
+
+    /// `Format:` line the plan was built from.
+    pub fn format(&self) -> &'a str {
+        self.format
+    }
+
+    /// Number of columns.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Field of every column, in `Format:` order.
+    pub fn fields(&self) -> &[Option<usize>] {
+        &self.fields
+    }
+
+    /// Name and field of every column, in `Format:` order.
+    pub fn columns(&self) -> impl Iterator<Item = (&'a str, Option<usize>)> + '_ {
+        self.format.split(',').map(str::trim).zip(self.fields.iter().copied())
+    }
+}
+
+impl<T> Clone for ColumnPlan<'_, T> {
+    fn clone(&self) -> Self {
+        Self { format: self.format, fields: self.fields.clone(), _type: PhantomData }
+    }
+}
+
+impl<T> Debug for ColumnPlan<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ColumnPlan").field("format", &self.format).field("fields", &self.fields).finish()
+    }
+}
+
+/// Reverse of [`FromLine`]: writes fields in the column order of `format`.
+/// Columns without a matching field get [`column_default`].
+pub trait ToLine {
+    fn to_line(&self, format: &str) -> String;
+}
+
+/// Value for a column a [`ToLine`] type has no field for: zero for numeric event columns,
+/// `Default` style, empty for anything else.
+pub fn column_default(column: &str) -> &'static str {
+    match column {
+        "Layer" | "MarginL" | "MarginR" | "MarginV" => "0",
+        "Start" | "End" => "0:00:00.00",
+        "Style" => "Default",
+        _ => "",
+    }
+}
+
+/// Collection for a `#[rest]` field of derived [`FromLine`] and [`ToLine`]: `(column, value)` pairs
+/// of columns that have no field. Also used for `#[rest]` and `#[prefix]` fields of derived [`FromLines`].
+pub trait RestColumns<'a>: Default + Extend<(&'a str, &'a str)> {
+    fn column(&self, name: &str) -> Option<&'a str>;
+    fn columns(&self) -> Vec<(&'a str, &'a str)>;
+}
+
+impl<'a> RestColumns<'a> for Vec<(&'a str, &'a str)> {
+    fn column(&self, name: &str) -> Option<&'a str> {
+        self.iter().find(|x| x.0 == name).map(|x| x.1)
+    }
+
+    fn columns(&self) -> Vec<(&'a str, &'a str)> {
+        self.clone()
+    }
+}
+
+impl<'a, S: std::hash::BuildHasher + Default> RestColumns<'a> for std::collections::HashMap<&'a str, &'a str, S> {
+    fn column(&self, name: &str) -> Option<&'a str> {
+        self.get(name).copied()
+    }
+
+    fn columns(&self) -> Vec<(&'a str, &'a str)> {
+        self.iter().map(|(k, v)| (*k, *v)).collect()
+    }
+}
+
+impl<'a> RestColumns<'a> for std::collections::BTreeMap<&'a str, &'a str> {
+    fn column(&self, name: &str) -> Option<&'a str> {
+        self.get(name).copied()
+    }
+
+    fn columns(&self) -> Vec<(&'a str, &'a str)> {
+        self.iter().map(|(k, v)| (*k, *v)).collect()
+    }
+}
+
+/// Reverse of [`FromLines`]: `(key, value)` lines of a key-value section, without section header.
+pub trait ToLines {
+    fn to_lines(&self) -> Vec<(String, String)>;
+}
+
+// Workaround to make macros work in this context
+mod asai {
+    pub use crate::*;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::asai;
+    use super::*;
+    use asai_macro::{AssEnum, FromLine, FromLines, ToLine, ToLines};
+
+    #[derive(FromLine, ToLine, Debug, PartialEq)]
+    struct Row<'a> {
+        #[name("Text")]
+        text: &'a str,
+        #[name("Layer")]
+        #[default(3)]
+        layer: u32,
+        #[name("Bold")]
+        bold: bool,
+    }
+
+    #[test]
+    fn to_line() {
+        let row = Row { text: "a, b", layer: 1, bold: true };
+        assert_eq!(row.to_line("Layer, Bold, Text"), "1,-1,a, b");
+        // Columns without a field get default values.
+        assert_eq!(row.to_line("Layer, Start, End, Style, Name, Text"), "1,0:00:00.00,0:00:00.00,Default,,a, b");
+        let line = row.to_line("Bold, Layer, Text");
+        assert_eq!(Row::from_line(&line, "Bold, Layer, Text").unwrap(), row);
+    }
+
+    #[test]
+    fn from_line_default() {
+        let row = Row::from_line("0,x", "Bold, Text").unwrap();
+        assert_eq!(row, Row { text: "x", layer: 3, bold: false });
+        assert!(Row::from_line("0,1", "Bold, Layer").is_err());
+    }
+
+    #[derive(FromLine, ToLine, Debug, PartialEq)]
+    #[rename_all("PascalCase")]
+    struct Renamed<'a> {
+        margin_l: u32,
+        #[name("Text")]
+        body: &'a str,
+    }
+
+    #[derive(FromLine, ToLine, Debug, PartialEq)]
+    #[rename_all("kebab-case")]
+    struct Generic<T, const N: usize> {
+        value_of: T,
+        count: u8,
+    }
+
+    #[test]
+    fn rename_all() {
+        let renamed = Renamed::from_line("5,a,b", "MarginL, Text").unwrap();
+        assert_eq!(renamed, Renamed { margin_l: 5, body: "a,b" });
+        assert_eq!(renamed.to_line("Text, MarginL"), "a,b,5");
+    }
+
+    #[test]
+    fn generics() {
+        let value: Generic<f64, 1> = Generic::from_line("2.5,3", "value-of, count").unwrap();
+        assert_eq!(value, Generic { value_of: 2.5, count: 3 });
+        assert_eq!(value.to_line("count, value-of"), "3,2.5");
+        assert!(Generic::<u32, 1>::from_line("2.5,3", "value-of, count").is_err());
+    }
+
+    fn parse_hex(s: &str) -> Result<u32, std::num::ParseIntError> {
+        u32::from_str_radix(s.trim_start_matches("0x"), 16)
+    }
+
+    #[derive(FromLine, ToLine, Debug, PartialEq)]
+    struct Extended<'a> {
+        #[name("Text")]
+        text: &'a str,
+        #[name("Reviewer")]
+        reviewer: Option<&'a str>,
+        #[name("Id")]
+        #[from_str]
+        id: std::net::Ipv4Addr,
+        #[name("Flags")]
+        #[with = "parse_hex"]
+        flags: u32,
+        #[rest]
+        other: Vec<(&'a str, &'a str)>,
+    }
+
+    #[test]
+    fn optional_columns() {
+        let format = "Id, Flags, Text";
+        let row = Extended::from_line("127.0.0.1,0x1f,a", format).unwrap();
+        assert_eq!(row.reviewer, None);
+        assert_eq!((row.id, row.flags), (std::net::Ipv4Addr::LOCALHOST, 31));
+        let row = Extended::from_line("127.0.0.1,ff,Bob,a", "Id, Flags, Reviewer, Text").unwrap();
+        assert_eq!(row.reviewer, Some("Bob"));
+        assert!(Extended::from_line("localhost,ff,a", format).is_err());
+        assert!(Extended::from_line("127.0.0.1,xyz,a", format).is_err());
+    }
+
+    #[test]
+    fn rest_columns() {
+        let format = "Layer, Id, Flags, Extra, Text";
+        let row = Extended::from_line("1,10.0.0.1,0,x,a, b", format).unwrap();
+        assert_eq!(row.other, vec![("Layer", "1"), ("Extra", "x")]);
+        assert_eq!(row.text, "a, b");
+        // `#[with]` fields are written with `ToLineField`, `#[from_str]` ones with `Display`.
+        assert_eq!(row.to_line("Extra, Reviewer, Flags, Id, Layer, Style, Text"), "x,,0,10.0.0.1,1,Default,a, b");
+        let map = std::collections::BTreeMap::from([("A", "1")]);
+        #[derive(FromLine, ToLine)]
+        struct Map<'a> {
+            #[rest]
+            columns: std::collections::BTreeMap<&'a str, &'a str>,
+        }
+        assert_eq!(Map::from_line("1", "A").unwrap().columns, map);
+        assert_eq!(Map { columns: map }.to_line("B, A"), ",1");
+    }
+
+    #[derive(FromLines, ToLines, Debug, PartialEq)]
+    struct Garbage<'a> {
+        #[name("Audio File")]
+        audio: Option<&'a str>,
+        #[name("Scroll Position")]
+        #[default(0)]
+        scroll: u32,
+        #[prefix("Video ")]
+        video: Vec<(&'a str, &'a str)>,
+        #[rest]
+        other: Vec<(&'a str, &'a str)>,
+    }
+
+    const GARBAGE: &str = "[Script Info]\nTitle: a\n\n[Aegisub Project Garbage]\nVideo File: a.mkv\nActive Line: 4\n\
+        Scroll Position: 2\nVideo Zoom Percent: 1\n\n[Events]\n";
+
+    #[test]
+    fn from_lines() {
+        let garbage: Garbage = Ass::parse_section("Aegisub Project Garbage", GARBAGE).unwrap();
+        assert_eq!(garbage.audio, None);
+        assert_eq!(garbage.scroll, 2);
+        assert_eq!(garbage.video, vec![("Video File", "a.mkv"), ("Video Zoom Percent", "1")]);
+        assert_eq!(garbage.other, vec![("Active Line", "4")]);
+        let garbage = Garbage::from_lines(&[("Audio File", "a.wav")]).unwrap();
+        assert_eq!((garbage.audio, garbage.scroll), (Some("a.wav"), 0));
+        assert!(Garbage::from_lines(&[("Scroll Position", "x")]).is_err());
+        assert_eq!(Ass::parse_section::<Garbage>("Fonts", GARBAGE).unwrap_err(), SectionParseError::SectionNotFound);
+    }
+
+    #[test]
+    fn to_lines() {
+        let garbage: Garbage = Ass::parse_section("Aegisub Project Garbage", GARBAGE).unwrap();
+        let lines: Vec<_> = garbage.to_lines().into_iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+        assert_eq!(lines, vec!["Scroll Position: 2", "Video File: a.mkv", "Video Zoom Percent: 1", "Active Line: 4"]);
+    }
+
+    #[derive(AssEnum, Debug, PartialEq)]
+    enum Effect {
+        #[value("Banner")]
+        Banner,
+        #[value(1)]
+        Karaoke,
+        #[value(-1)]
+        Reverse,
+        Scroll,
+        #[fallback]
+        Other(String),
+    }
+
+    #[derive(AssEnum, Debug, PartialEq)]
+    enum Strict {
+        #[value(0)]
+        Off,
+        #[fallback]
+        #[value(1)]
+        On,
+    }
+
+    #[derive(AssEnum, Debug, PartialEq)]
+    enum NoFallback {
+        A,
+    }
+
+    #[test]
+    fn ass_enum() {
+        assert_eq!(" Banner ".parse().ok(), Some(Effect::Banner));
+        assert_eq!("1".parse().ok(), Some(Effect::Karaoke));
+        assert_eq!("-1".parse().ok(), Some(Effect::Reverse));
+        assert_eq!("Scroll".parse().ok(), Some(Effect::Scroll));
+        assert_eq!("Scroll up".parse().ok(), Some(Effect::Other("Scroll up".to_string())));
+        assert_eq!(Effect::Karaoke.to_string(), "1");
+        assert_eq!(Effect::Other("x".to_string()).to_string(), "x");
+        assert_eq!("7".parse().ok(), Some(Strict::On));
+        assert_eq!(Strict::On.to_string(), "1");
+        assert!("B".parse::<NoFallback>().is_err());
+    }
+
+    #[derive(FromLine, ToLine, Debug, PartialEq)]
+    struct WithEnum {
+        #[name("Effect")]
+        effect: Effect,
+        #[name("Mode")]
+        mode: Option<Strict>,
+    }
+
+    #[test]
+    fn ass_enum_fields() {
+        let row = WithEnum::from_line("0,Banner", "Mode, Effect").unwrap();
+        assert_eq!(row, WithEnum { effect: Effect::Banner, mode: Some(Strict::Off) });
+        assert_eq!(row.to_line("Effect, Mode"), "Banner,0");
+        assert!(WithEnum::from_line("1", "Mode").is_err());
+    }
+
+    #[test]
+    fn event_and_style_round_trip() {
+        let line = "1,0:01:02.34,1:00:00.00,Sign,Bob,1,2,3,Scroll up;1;2,{\\b1}a, b";
+        let event = Event::from_line(line, EVENT_FORMAT).unwrap();
+        assert_eq!(event.to_line(), line);
+        let line = "Default,Arial,20.5,&H00FFFFFF,&H000000FF,&H00000000,&H80000000,-1,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1";
+        let style = Style::from_line(line, STYLE_FORMAT).unwrap();
+        assert_eq!(style.to_line(), line);
+    }
+
+    #[test]
+    fn planned_line_matches_from_line() {
+        let event = Event::from_line("1,0:00:01.00,0:00:02.00,Sign,Bob,0,0,0,,a, b", EVENT_FORMAT).unwrap();
+        let cases = [
+            // Reordered.
+            ("Start, End, Layer, Style, Name, MarginL, MarginR, MarginV, Effect, Text", "0:00:01.00,0:00:02.00,1,Sign,Bob,0,0,0,,a, b"),
+            // Duplicate, the last one is used.
+            ("Layer, Start, Layer, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text", "5,0:00:01.00,1,0:00:02.00,Sign,Bob,0,0,0,,a, b"),
+            // Missing.
+            ("Layer, Start, End, Style, Name, Text", "1,0:00:01.00,0:00:02.00,Sign,Bob,a, b"),
+            // Extra.
+            ("Layer, Start, End, Style, Name, Extra, MarginL, MarginR, MarginV, Effect, Text", "1,0:00:01.00,0:00:02.00,Sign,Bob,x,0,0,0,,a, b"),
+        ];
+        for (format, line) in cases {
+            let plan = ColumnPlan::new(format);
+            assert_eq!(plan.format(), format);
+            let planned = Event::from_planned_line(line, &plan).ok();
+            assert_eq!(planned, Event::from_line(line, format).ok(), "{}", format);
+            assert_eq!(planned.as_ref(), Some(&event), "{}", format);
+        }
+
+        // Extra columns go to `#[rest]` under their own names, duplicates included.
+        let format = "Extra, Id, Flags, Extra, Text";
+        let plan = ColumnPlan::new(format);
+        let planned = Extended::from_planned_line("x,127.0.0.1,1f,y,a", &plan).unwrap();
+        assert_eq!(Some(&planned), Extended::from_line("x,127.0.0.1,1f,y,a", format).ok().as_ref());
+        assert_eq!(planned.other, vec![("Extra", "x"), ("Extra", "y")]);
+        assert_eq!(plan.columns().nth(1), Some(("Id", Some(2))));
+    }
+
+    #[test]
+    fn styles_diagnostics() {
+        let ass = crate::parse_str("[V4+ Styles]\nFormat: Name, Fontname, Fontname\nStyle: a,Arial,Verdana\n[Events]\n").unwrap();
+        assert!(ass.styles.diagnostics.contains(&FormatDiagnostic::Duplicate("Fontname")));
+        assert!(ass.styles.diagnostics.contains(&FormatDiagnostic::Missing("Fontsize")));
+        assert!(crate::parse_str("[V4+ Styles]\n[Events]\n").unwrap().styles.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn authors() {
+        let lines = [
+            ("Title", "a"),
+            ("Original Script", "Foo, Bar & Baz"),
+            ("Original Translation", "Qux/ Quux ;"),
+            ("Original Editing", "Corge"),
+            ("Original Timing", ""),
+            ("Script Updated By", "Grault"),
+            ("Update Details", "Fixed typos, retimed"),
+            ("Original Author", "Garply"),
+        ];
+        let info = ScriptInfo::from_lines(&lines).unwrap();
+        let authors = info.authors;
+        assert_eq!(authors.script(), Some("Foo, Bar & Baz"));
+        assert_eq!(authors.script_authors().collect::<Vec<_>>(), vec!["Foo", "Bar", "Baz"]);
+        assert_eq!(authors.translation_authors().collect::<Vec<_>>(), vec!["Qux", "Quux"]);
+        assert_eq!(authors.editing_authors().collect::<Vec<_>>(), vec!["Corge"]);
+        assert_eq!(authors.timing(), Some(""));
+        assert_eq!(authors.timing_authors().count(), 0);
+        assert_eq!(authors.updated_by_authors().collect::<Vec<_>>(), vec!["Grault"]);
+        assert_eq!(authors.update_details(), Some("Fixed typos, retimed"));
+        assert!(!authors.is_empty());
+        // Other keys are not authors keys, even if they look like ones.
+        assert_eq!(info.title, Some("a"));
+        assert_eq!(info.other, vec![("Original Author", "Garply")]);
+
+        let mut authors = Authors::default();
+        assert!(authors.is_empty());
+        assert!(!authors.set("Title", "a"));
+        assert!(authors.set("Original Timing", "Foo"));
+        assert_eq!(authors.timing(), Some("Foo"));
+        assert_eq!(Authors::split(" ; ,& ").count(), 0);
+    }
 }
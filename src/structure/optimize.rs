@@ -0,0 +1,319 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt::Write;
+use crate::structure::base_types::Color;
+use crate::structure::event::Event;
+use crate::structure::state::StyleState;
+use crate::structure::style::Alignment;
+use crate::structure::tags::{self, format_number, parse_number, parse_override, Block, OverrideItem, Tag};
+use crate::structure::{Events, Styles};
+
+/// Parts of [`StyleState`] set by override tags. Colors and alphas are separate, as tags set them separately.
+const PROPERTIES: [&str; 29] = [
+    "fn", "fs", "fscx", "fscy", "fsp", "fe", "b", "i", "u", "s", "frx", "fry", "frz", "fax", "fay", "xbord", "ybord",
+    "xshad", "yshad", "blur", "be", "1c", "2c", "3c", "4c", "1a", "2a", "3a", "4a",
+];
+
+/// Tags with plain numeric argument.
+const NUMERIC: [&str; 32] = [
+    "fs", "fscx", "fscy", "fsp", "fe", "b", "i", "u", "s", "frx", "fry", "frz", "fr", "fax", "fay", "bord", "xbord",
+    "ybord", "shad", "xshad", "yshad", "blur", "be", "k", "K", "kf", "ko", "an", "a", "q", "p", "pbo",
+];
+
+/// Properties set by a tag, empty for tags that don't change [`StyleState`].
+fn properties(name: &str) -> &'static [&'static str] {
+    match name {
+        "bord" => &PROPERTIES[15..17],
+        "shad" => &PROPERTIES[17..19],
+        "c" => &PROPERTIES[21..22],
+        "fr" => &PROPERTIES[12..13],
+        "alpha" => &PROPERTIES[25..29],
+        "r" => &PROPERTIES,
+        _ => match PROPERTIES.iter().position(|x| *x == name) {
+            Some(i) => &PROPERTIES[i..i + 1],
+            None => &[],
+        },
+    }
+}
+
+/// Tags only the first of which has an effect in the whole event.
+fn first_wins_group(name: &str) -> Option<&'static str> {
+    match name {
+        "pos" | "move" => Some("position"),
+        "org" => Some("org"),
+        "an" | "a" => Some("alignment"),
+        "fad" | "fade" => Some("fade"),
+        _ => None,
+    }
+}
+
+fn normalize_number(arg: &mut Cow<'_, str>) {
+    if let Some(x) = parse_number(arg) {
+        *arg = Cow::Owned(format_number(x));
+    }
+}
+
+/// Rewrites numbers, colors and alphas of a tag in the shortest form.
+fn normalize(tag: &mut Tag<'_>) {
+    match tag.name {
+        "pos" | "move" | "org" | "fad" | "fade" => tag.args.iter_mut().for_each(normalize_number),
+        "clip" | "iclip" if tag.args.len() == 4 => tag.args.iter_mut().for_each(normalize_number),
+        "t" => {
+            let Some((last, times)) = tag.args.split_last_mut() else { return };
+            times.iter_mut().for_each(normalize_number);
+            if last.trim_start().starts_with('\\') {
+                let mut nested = String::new();
+                for item in parse_override(last) {
+                    match item {
+                        OverrideItem::Tag(mut x) => {
+                            normalize(&mut x);
+                            write!(nested, "{}", x).unwrap();
+                        }
+                        OverrideItem::Comment(x) => nested.push_str(x),
+                    }
+                }
+                *last = Cow::Owned(nested);
+            }
+        }
+        "c" | "1c" | "2c" | "3c" | "4c" => {
+            if let Some(c) = tag.arg(0).and_then(|x| x.parse::<Color>().ok()) {
+                tag.args = vec![Cow::Owned(format!("&H{:02X}{:02X}{:02X}&", c.b, c.g, c.r))];
+            }
+        }
+        "alpha" | "1a" | "2a" | "3a" | "4a" => {
+            let hex = tag.arg(0).map(|x| x.trim().trim_start_matches('&').trim_start_matches(['H', 'h']).trim_end_matches('&'));
+            if let Some(a) = hex.and_then(|x| u8::from_str_radix(x, 16).ok()) {
+                tag.args = vec![Cow::Owned(format!("&H{:02X}&", a))];
+            }
+        }
+        name if !tag.parens && NUMERIC.contains(&name) => tag.args.iter_mut().for_each(normalize_number),
+        _ => {}
+    }
+}
+
+struct Slot<'a> {
+    item: OverrideItem<'a>,
+    /// Properties not set again later in the same group.
+    live: Vec<&'static str>,
+    /// Kept even if overridden: not a style tag, or read by a later `\t` of the group as its starting value.
+    keep: bool,
+}
+
+struct Optimizer<'a, 's> {
+    styles: &'a Styles<'s>,
+    base: StyleState<'a>,
+    state: StyleState<'a>,
+    /// Properties changed by `\t`, so their current value is unknown.
+    animated: HashSet<&'static str>,
+    seen: HashSet<&'static str>,
+    drawing: f64,
+}
+
+impl<'a> Optimizer<'a, '_> {
+    /// Writes override blocks between two pieces of text as one block. `trailing` is set if no text follows.
+    fn group(&mut self, items: Vec<OverrideItem<'a>>, trailing: bool, out: &mut String) {
+        // Drop tags overridden before any text is drawn.
+        let mut slots: Vec<Slot> = vec![];
+        for item in items {
+            if let OverrideItem::Tag(tag) = &item {
+                let set = properties(tag.name);
+                if trailing && !set.is_empty() {
+                    continue;
+                }
+                let read: Vec<&str> = tag
+                    .nested()
+                    .iter()
+                    .flat_map(|x| match x {
+                        OverrideItem::Tag(x) => properties(x.name),
+                        OverrideItem::Comment(_) => &[],
+                    })
+                    .copied()
+                    .collect();
+                for slot in &mut slots {
+                    slot.keep |= slot.live.iter().any(|x| read.contains(x));
+                    slot.live.retain(|x| !set.contains(x));
+                }
+                slots.push(Slot { live: set.to_vec(), keep: set.is_empty(), item });
+            } else {
+                slots.push(Slot { live: vec![], keep: true, item });
+            }
+        }
+        slots.retain(|x| x.keep || !x.live.is_empty());
+
+        // Drop tags that don't change anything.
+        let mut kept = vec![];
+        for slot in slots {
+            let OverrideItem::Tag(tag) = &slot.item else {
+                kept.push(slot.item);
+                continue;
+            };
+            let set = properties(tag.name);
+            let keep = if !set.is_empty() {
+                let mut next = self.state.clone();
+                next.apply(tag, &self.base, self.styles);
+                let animated = set.iter().any(|x| self.animated.contains(x));
+                set.iter().for_each(|x| {
+                    self.animated.remove(x);
+                });
+                let changed = next != self.state;
+                self.state = next;
+                animated || changed
+            } else if let Some(group) = first_wins_group(tag.name) {
+                let first = self.seen.insert(group);
+                // Alignment of the style doesn't need restating.
+                let redundant = group == "alignment"
+                    && tag.number(0).map(|x| x as u8).and_then(|x| match tag.name {
+                        "an" => Alignment::from_numpad(x),
+                        _ => Alignment::from_legacy(x),
+                    }) == Some(self.base.alignment);
+                first && !redundant
+            } else if tag.name == "p" {
+                let scale = tag.number(0).unwrap_or(0.0);
+                let changed = scale != self.drawing;
+                self.drawing = scale;
+                changed
+            } else {
+                if tag.name == "t" {
+                    for nested in tag.nested() {
+                        if let OverrideItem::Tag(x) = nested {
+                            self.animated.extend(properties(x.name));
+                        }
+                    }
+                }
+                true
+            };
+            if keep {
+                kept.push(slot.item);
+            }
+        }
+
+        if kept.is_empty() {
+            return;
+        }
+        out.push('{');
+        let mut after_tag = false;
+        for item in kept {
+            match item {
+                OverrideItem::Tag(mut tag) => {
+                    normalize(&mut tag);
+                    write!(out, "{}", tag).unwrap();
+                    after_tag = true;
+                }
+                OverrideItem::Comment(comment) => {
+                    // Comment after a tag would be read as its argument.
+                    if after_tag {
+                        out.push_str("}{");
+                    }
+                    out.push_str(comment);
+                    after_tag = false;
+                }
+            }
+        }
+        out.push('}');
+    }
+}
+
+impl Event<'_> {
+    /// Text with redundant and overridden tags removed, adjacent override blocks merged
+    /// and numbers normalized. Renders the same as the original text.
+    pub fn optimized_text(&self, styles: &Styles<'_>) -> String {
+        let base = StyleState::for_event(self, styles);
+        let mut optimizer = Optimizer {
+            styles,
+            state: base.clone(),
+            base,
+            animated: HashSet::new(),
+            seen: HashSet::new(),
+            drawing: 0.0,
+        };
+        let mut out = String::with_capacity(self.text.len());
+        let mut group = vec![];
+        for block in tags::parse(&self.text) {
+            match block {
+                Block::Override(items) => group.extend(items),
+                block => {
+                    optimizer.group(std::mem::take(&mut group), false, &mut out);
+                    write!(out, "{}", block).unwrap();
+                }
+            }
+        }
+        optimizer.group(group, true, &mut out);
+        out
+    }
+}
+
+impl Events<'_> {
+    /// Replaces text of all events with [`Event::optimized_text`]. Returns number of changed events.
+    pub fn optimize_tags(&mut self, styles: &Styles<'_>) -> usize {
+        let mut changed = 0;
+        for (_, event) in &mut self.events {
+            let text = event.optimized_text(styles);
+            if text != event.text {
+                event.text = Cow::Owned(text);
+                changed += 1;
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    const SCRIPT: &str = "[V4+ Styles]
+Style: Default,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1
+
+[Events]
+";
+
+    fn optimized(text: &str) -> String {
+        let data = format!("{}Dialogue: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,{}\n", SCRIPT, text);
+        let ass = crate::parse_str(&data).unwrap();
+        ass.events.events[0].1.optimized_text(&ass.styles)
+    }
+
+    #[test]
+    fn style_values() {
+        assert_eq!(optimized("{\\fs20\\b0\\bord2}a"), "a");
+        assert_eq!(optimized("{\\fs30}a{\\fs30}b"), "{\\fs30}ab");
+    }
+
+    #[test]
+    fn overridden_tags() {
+        assert_eq!(optimized("{\\fs30\\fs40}a"), "{\\fs40}a");
+        assert_eq!(optimized("{\\pos(1,2)}a{\\pos(3,4)}b"), "{\\pos(1,2)}ab");
+        assert_eq!(optimized("{\\bord3\\xbord4\\ybord4}a"), "{\\xbord4\\ybord4}a");
+    }
+
+    #[test]
+    fn merge_and_normalize() {
+        assert_eq!(optimized("{\\i1}{\\fs30.500}a"), "{\\i1\\fs30.5}a");
+        assert_eq!(optimized("{\\pos(1.0,2.50)}a"), "{\\pos(1,2.5)}a");
+    }
+
+    #[test]
+    fn trailing_tags() {
+        assert_eq!(optimized("a{\\i1}"), "a");
+    }
+
+    #[test]
+    fn animated_tags_are_kept() {
+        assert_eq!(optimized("{\\fs30\\t(\\fs20)}a"), "{\\fs30\\t(\\fs20)}a");
+    }
+
+    #[test]
+    fn comments_are_kept() {
+        assert_eq!(optimized("{note\\i1}a"), "{note\\i1}a");
+    }
+
+    #[test]
+    fn optimize_tags_counts_changes() {
+        let data = format!(
+            "{}Dialogue: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,{{\\fs20}}a\nDialogue: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,b\n",
+            SCRIPT
+        );
+        let mut ass = crate::parse_str(&data).unwrap();
+        let styles = ass.styles.clone();
+        assert_eq!(ass.events.optimize_tags(&styles), 1);
+        assert_eq!(ass.events.events[0].1.text, "a");
+    }
+}
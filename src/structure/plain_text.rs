@@ -0,0 +1,118 @@
+use std::ops::Range;
+use crate::structure::event::Event;
+use crate::structure::tags::{parse_spans, Block};
+use crate::structure::WrapStyle;
+
+/// Visible text of an event with mapping back to the original text.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct PlainText {
+    pub text: String,
+    /// Original range of each byte of `text`.
+    spans: Vec<Range<usize>>,
+    original_len: usize,
+}
+
+impl PlainText {
+    /// Extracts visible text: override blocks, comments and drawings are dropped,
+    /// `\h` becomes a non-breaking space and `\N` a newline.
+    /// `\n` is a newline only with [`WrapStyle::NoWrap`], otherwise it is a space.
+    pub fn new(text: &str, wrap_style: WrapStyle) -> Self {
+        let mut plain = Self { original_len: text.len(), ..Default::default() };
+        for (range, block) in parse_spans(text) {
+            let Block::Text(_) = block else { continue };
+            let mut chars = text[range.clone()].char_indices().peekable();
+            while let Some((i, c)) = chars.next() {
+                let start = range.start + i;
+                let escaped = match (c, chars.peek()) {
+                    ('\\', Some((_, 'N'))) => Some('\n'),
+                    ('\\', Some((_, 'n'))) if matches!(wrap_style, WrapStyle::NoWrap) => Some('\n'),
+                    ('\\', Some((_, 'n'))) => Some(' '),
+                    ('\\', Some((_, 'h'))) => Some('\u{a0}'),
+                    _ => None,
+                };
+                match escaped {
+                    Some(escaped) => {
+                        chars.next();
+                        plain.push(escaped, start..start + 2);
+                    }
+                    None => plain.push(c, start..start + c.len_utf8()),
+                }
+            }
+        }
+        plain
+    }
+
+    fn push(&mut self, c: char, original: Range<usize>) {
+        self.text.push(c);
+        for _ in 0..c.len_utf8() {
+            self.spans.push(original.clone());
+        }
+    }
+
+    /// Offset in original text corresponding to `offset` in plain text.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        match self.spans.get(offset) {
+            Some(span) => span.start,
+            None => self.spans.last().map(|x| x.end).unwrap_or(self.original_len),
+        }
+    }
+
+    /// Range in original text covering `range` of plain text.
+    ///
+    /// Empty ranges map to an empty range. Tags between mapped characters are included.
+    pub fn original_range(&self, range: Range<usize>) -> Range<usize> {
+        let start = self.original_offset(range.start);
+        if range.is_empty() {
+            return start..start;
+        }
+        let end = self.spans.get(range.end - 1).map(|x| x.end).unwrap_or(start);
+        start..end
+    }
+
+    /// Pieces of original text `range` of plain text comes from, without tags between them.
+    /// Parts of `range` past the end of plain text are ignored.
+    pub fn original_segments(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        let end = range.end.min(self.spans.len());
+        let start = range.start.min(end);
+        let mut segments: Vec<Range<usize>> = vec![];
+        for span in &self.spans[start..end] {
+            match segments.last_mut() {
+                Some(last) if last.end == span.start => last.end = span.end,
+                Some(last) if last.end >= span.end => {} // Bytes of the same character
+                _ => segments.push(span.clone()),
+            }
+        }
+        segments
+    }
+}
+
+impl<'a> Event<'a> {
+    /// Visible text of the event, see [`PlainText::new`].
+    pub fn plain_text(&self, wrap_style: WrapStyle) -> PlainText {
+        PlainText::new(&self.text, wrap_style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments() {
+        let plain = PlainText::new("a{\\b1}bc\\Nd", WrapStyle::SmartConstant);
+        assert_eq!(plain.text, "abc\nd");
+        assert_eq!(plain.original_segments(0..3), vec![0..1, 6..8]);
+        assert_eq!(plain.original_segments(3..5), vec![8..11]);
+        assert_eq!(plain.original_range(0..2), 0..7);
+    }
+
+    #[test]
+    fn segments_out_of_range() {
+        let plain = PlainText::new("ab", WrapStyle::SmartConstant);
+        assert_eq!(plain.original_segments(1..10), vec![1..2]);
+        assert!(plain.original_segments(5..10).is_empty());
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = plain.original_segments(2..1);
+        assert!(reversed.is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::ops::Index;
+use crate::structure::base_types::{LineField, ToLineField};
+use crate::structure::{FromLine, InvalidValue, ToLine};
+
+/// Line of a formatted section with columns known only at runtime, like `FormattedSection<String, Record>`
+/// for sections of other tools. Columns keep the order of `Format:`.
+///
+/// If a column appears more than once, getters and setters use the last one, like derived [`FromLine`] types do.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Record<'a> {
+    columns: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+}
+
+impl<'a> Record<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// `(name, value)` pairs in column order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.columns.iter().map(|(k, v)| (k.as_ref(), v.as_ref()))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.columns.iter().map(|x| x.0.as_ref())
+    }
+
+    /// `Format:` value for this record, columns in their order.
+    pub fn format(&self) -> String {
+        self.names().collect::<Vec<_>>().join(", ")
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.columns.iter().rposition(|x| x.0 == name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.position(name).map(|i| self.columns[i].1.as_ref())
+    }
+
+    /// Parses a column like a field of a derived [`FromLine`] type. Missing column is an error too.
+    pub fn get_as<'b, T>(&'b self, name: &str) -> Result<T, InvalidValue>
+    where
+        T: TryFrom<LineField<'b>>,
+        InvalidValue: From<T::Error>,
+    {
+        Ok(LineField::new(self.get(name).ok_or(InvalidValue)?).try_into()?)
+    }
+
+    /// `(name, value)` of a column by its index.
+    pub fn column(&self, index: usize) -> Option<(&str, &str)> {
+        self.columns.get(index).map(|(k, v)| (k.as_ref(), v.as_ref()))
+    }
+
+    pub fn column_as<'b, T>(&'b self, index: usize) -> Result<T, InvalidValue>
+    where
+        T: TryFrom<LineField<'b>>,
+        InvalidValue: From<T::Error>,
+    {
+        Ok(LineField::new(self.column(index).ok_or(InvalidValue)?.1).try_into()?)
+    }
+
+    /// Sets value of a column. Columns that don't exist are added at the end.
+    pub fn set(&mut self, name: impl Into<Cow<'a, str>>, value: impl Into<Cow<'a, str>>) {
+        let name = name.into();
+        match self.position(&name) {
+            Some(i) => self.columns[i].1 = value.into(),
+            None => self.columns.push((name, value.into())),
+        }
+    }
+
+    /// Sets value of a column written like a field of a derived [`ToLine`] type.
+    pub fn set_as<T: ToLineField>(&mut self, name: impl Into<Cow<'a, str>>, value: &T) {
+        self.set(name, value.to_line_field())
+    }
+
+    /// Sets value of a column by its index. Panics if there is no such column.
+    pub fn set_column(&mut self, index: usize, value: impl Into<Cow<'a, str>>) {
+        self.columns[index].1 = value.into();
+    }
+
+    /// Removes a column, returns its value.
+    pub fn remove(&mut self, name: &str) -> Option<Cow<'a, str>> {
+        self.position(name).map(|i| self.columns.remove(i).1)
+    }
+
+    pub fn into_owned(self) -> Record<'static> {
+        let columns = self
+            .columns
+            .into_iter()
+            .map(|(k, v)| (Cow::Owned(k.into_owned()), Cow::Owned(v.into_owned())))
+            .collect();
+        Record { columns }
+    }
+}
+
+impl<'a> FromLine<'a> for Record<'a> {
+    /// Never fails. Columns without a value in the line are left out.
+    fn from_line(item: &'a str, format: &'a str) -> Result<Self, InvalidValue> {
+        let names: Vec<_> = format.split(',').map(str::trim).collect();
+        let columns = names
+            .iter()
+            .zip(item.splitn(names.len(), ','))
+            .map(|(k, v)| (Cow::Borrowed(*k), Cow::Borrowed(v)))
+            .collect();
+        Ok(Self { columns })
+    }
+}
+
+impl ToLine for Record<'_> {
+    /// Columns the record doesn't have are written empty: unlike derived types, a record has no field
+    /// that a default value would stand for.
+    fn to_line(&self, format: &str) -> String {
+        format
+            .split(',')
+            .map(str::trim)
+            .map(|k| self.get(k).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Values in column order, the line for [`Record::format`].
+impl Display for Record<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, (_, value)) in self.columns.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", value)?;
+        }
+        Ok(())
+    }
+}
+
+impl Index<&str> for Record<'_> {
+    type Output = str;
+
+    /// Panics if there is no such column.
+    fn index(&self, name: &str) -> &str {
+        self.get(name).unwrap_or_else(|| panic!("no column {:?}", name))
+    }
+}
+
+impl Index<usize> for Record<'_> {
+    type Output = str;
+
+    fn index(&self, index: usize) -> &str {
+        &self.columns[index].1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_line_missing_columns() {
+        let record = Record::from_line("1,a, b", "Layer, Text").unwrap();
+        assert_eq!(record.to_line("Text"), "a, b");
+        // Missing columns are empty, not `column_default`.
+        assert_eq!(record.to_line("Layer, Start, Style, MarginL, Text"), "1,,,,a, b");
+        assert_eq!(record.to_string(), "1,a, b");
+    }
+
+    #[test]
+    fn get_and_set() {
+        let mut record = Record::from_line("1,x,2", "Layer, Style, Layer").unwrap();
+        assert_eq!(record.len(), 3);
+        assert_eq!(record.get_as::<u32>("Layer").ok(), Some(2));
+        assert_eq!(record.column(2), Some(("Layer", "2")));
+        record.set("Style", "Sign");
+        record.set("Text", "a");
+        assert_eq!(record.format(), "Layer, Style, Layer, Text");
+        assert_eq!(&record["Style"], "Sign");
+        assert_eq!(record.remove("Text").as_deref(), Some("a"));
+        assert!(record.get("Text").is_none());
+        assert!(record.get_as::<u32>("Text").is_err());
+    }
+}
@@ -0,0 +1,228 @@
+use std::borrow::Cow;
+use crate::structure::tags::{self, format_number, transform_drawing, Block, OverrideItem, Tag};
+use crate::structure::{Ass, Resolution};
+
+/// How to deal with source and destination resolutions having different aspect ratios.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum AspectRatioMode {
+    /// Scale each axis independently. Text gets stretched together with video.
+    #[default]
+    Stretch,
+    /// Keep aspect ratio, fit script width and center it vertically. Adds bars on top and bottom
+    /// when the destination is taller than the source, crops top and bottom when it is wider.
+    FitWidth,
+    /// Keep aspect ratio, fit script height and center it horizontally. Adds bars on the sides
+    /// when the destination is wider than the source, crops the sides when it is taller.
+    FitHeight,
+}
+
+/// Mapping from source script coordinates to destination ones.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Resampler {
+    pub scale_x: f64,
+    pub scale_y: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+}
+
+impl Resampler {
+    pub fn new(from: (u64, u64), to: (u64, u64), mode: AspectRatioMode) -> Self {
+        let (fx, fy) = (from.0.max(1) as f64, from.1.max(1) as f64);
+        let (tx, ty) = (to.0 as f64, to.1 as f64);
+        match mode {
+            AspectRatioMode::Stretch => Self {
+                scale_x: tx / fx,
+                scale_y: ty / fy,
+                offset_x: 0.0,
+                offset_y: 0.0,
+            },
+            AspectRatioMode::FitWidth => {
+                let s = tx / fx;
+                Self { scale_x: s, scale_y: s, offset_x: 0.0, offset_y: (ty - fy * s) / 2.0 }
+            }
+            AspectRatioMode::FitHeight => {
+                let s = ty / fy;
+                Self { scale_x: s, scale_y: s, offset_x: (tx - fx * s) / 2.0, offset_y: 0.0 }
+            }
+        }
+    }
+
+    /// Horizontal stretch applied to text, so it follows stretched video.
+    pub fn aspect(&self) -> f64 {
+        self.scale_x / self.scale_y
+    }
+
+    pub fn point(&self, x: f64, y: f64) -> (f64, f64) {
+        (x * self.scale_x + self.offset_x, y * self.scale_y + self.offset_y)
+    }
+
+    fn margin_x(&self, m: u32) -> u32 {
+        (m as f64 * self.scale_x + self.offset_x).round().max(0.0) as u32
+    }
+
+    fn margin_y(&self, m: u32) -> u32 {
+        (m as f64 * self.scale_y + self.offset_y).round().max(0.0) as u32
+    }
+
+    /// Rescales override tags and drawings in event text.
+    pub fn text(&self, text: &str) -> String {
+        let blocks: Vec<Block> = tags::parse(text)
+            .into_iter()
+            .map(|block| match block {
+                Block::Override(items) => Block::Override(self.items(items)),
+                Block::Drawing { scale, commands } => Block::Drawing {
+                    scale,
+                    commands: transform_drawing(&commands, |x, y| (x * self.scale_x, y * self.scale_y)).into(),
+                },
+                block => block,
+            })
+            .collect();
+        tags::to_text(&blocks)
+    }
+
+    fn items<'a>(&self, items: Vec<OverrideItem<'a>>) -> Vec<OverrideItem<'a>> {
+        items
+            .into_iter()
+            .map(|item| match item {
+                OverrideItem::Tag(tag) => OverrideItem::Tag(self.tag(tag)),
+                item => item,
+            })
+            .collect()
+    }
+
+    fn tag<'a>(&self, mut tag: Tag<'a>) -> Tag<'a> {
+        let scale = |tag: &mut Tag, i: usize, by: f64| {
+            if let Some(v) = tag.number(i) {
+                tag.args[i] = format_number(v * by).into();
+            }
+        };
+        match (tag.name, tag.args.len()) {
+            ("fs" | "bord" | "shad" | "blur" | "ybord" | "yshad" | "pbo", _) => scale(&mut tag, 0, self.scale_y),
+            ("fsp" | "xbord" | "xshad", _) => scale(&mut tag, 0, self.scale_x),
+            ("fscx", _) => scale(&mut tag, 0, self.aspect()),
+            ("pos" | "org", 2) | ("move", 4 | 6) | ("clip" | "iclip", 4) => {
+                let count = if tag.args.len() == 2 { 1 } else { 2 };
+                for p in 0..count {
+                    if let (Some(x), Some(y)) = (tag.number(p * 2), tag.number(p * 2 + 1)) {
+                        let (x, y) = self.point(x, y);
+                        tag.args[p * 2] = format_number(x).into();
+                        tag.args[p * 2 + 1] = format_number(y).into();
+                    }
+                }
+            }
+            ("clip" | "iclip", 1 | 2) => {
+                // Vector clip coordinates are multiplied by 2^(scale - 1).
+                let unit = match tag.args.len() {
+                    2 => 2f64.powf(tag.number(0).unwrap_or(1.0) - 1.0),
+                    _ => 1.0,
+                };
+                let last = tag.args.len() - 1;
+                let commands = transform_drawing(&tag.args[last], |x, y| {
+                    (x * self.scale_x + self.offset_x * unit, y * self.scale_y + self.offset_y * unit)
+                });
+                tag.args[last] = commands.into();
+            }
+            ("t", _) => {
+                let nested: Vec<OverrideItem> = self.items(tag.nested());
+                let nested: String = nested.iter().map(|x| x.to_string()).collect();
+                if let Some(last) = tag.args.last_mut() {
+                    if last.trim_start().starts_with('\\') {
+                        *last = Cow::Owned(nested);
+                    }
+                }
+            }
+            _ => {}
+        }
+        tag
+    }
+}
+
+impl<'a> Ass<'a> {
+    /// Changes script resolution, rescaling styles, margins, override tags and drawings.
+    pub fn resample(&mut self, to: Resolution, mode: AspectRatioMode) {
+        let resampler = Resampler::new(self.info.resolution.resolved(), to.resolved(), mode);
+        for style in &mut self.styles.styles {
+            style.font_size = (style.font_size as f64 * resampler.scale_y) as f32;
+            style.outline = (style.outline as f64 * resampler.scale_y) as f32;
+            style.shadow = (style.shadow as f64 * resampler.scale_y) as f32;
+            style.spacing = (style.spacing as f64 * resampler.scale_x) as f32;
+            style.scale_x = (style.scale_x as f64 * resampler.aspect()) as f32;
+            style.margin_l = resampler.margin_x(style.margin_l);
+            style.margin_r = resampler.margin_x(style.margin_r);
+            style.margin_v = resampler.margin_y(style.margin_v);
+        }
+        for (_, event) in &mut self.events.events {
+            // Zero margin means "use style's margin", so it is kept.
+            if event.margin_l != 0 {
+                event.margin_l = resampler.margin_x(event.margin_l);
+            }
+            if event.margin_r != 0 {
+                event.margin_r = resampler.margin_x(event.margin_r);
+            }
+            if event.margin_v != 0 {
+                event.margin_v = resampler.margin_y(event.margin_v);
+            }
+            if event.text.contains('{') {
+                event.text = Cow::Owned(resampler.text(&event.text));
+            }
+        }
+        self.info.resolution = to;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure::Resolution;
+
+    #[test]
+    fn stretch_scales_axes_independently() {
+        let r = Resampler::new((640, 480), (1920, 1080), AspectRatioMode::Stretch);
+        assert_eq!(r.point(640.0, 480.0), (1920.0, 1080.0));
+        assert_eq!(r.aspect(), 3.0 / 2.25);
+    }
+
+    #[test]
+    fn fit_width_adds_bars_on_taller_destination() {
+        let r = Resampler::new((1920, 1080), (1440, 1080), AspectRatioMode::FitWidth);
+        assert_eq!(r.scale_x, 0.75);
+        assert_eq!(r.offset_y, 135.0);
+        assert_eq!(r.point(0.0, 0.0), (0.0, 135.0));
+        assert_eq!(r.point(1920.0, 1080.0), (1440.0, 945.0));
+    }
+
+    #[test]
+    fn fit_height_adds_bars_on_wider_destination() {
+        let r = Resampler::new((640, 480), (1920, 1080), AspectRatioMode::FitHeight);
+        assert_eq!(r.scale_y, 2.25);
+        assert_eq!(r.point(0.0, 0.0), (240.0, 0.0));
+        assert_eq!(r.point(640.0, 480.0), (1680.0, 1080.0));
+        assert_eq!(r.aspect(), 1.0);
+    }
+
+    #[test]
+    fn text_tags() {
+        let r = Resampler::new((640, 360), (1280, 720), AspectRatioMode::Stretch);
+        assert_eq!(r.text("{\\pos(10,20)\\fs30\\bord1.5}Hi"), "{\\pos(20,40)\\fs60\\bord3}Hi");
+        assert_eq!(r.text("{\\move(0,0,5,5,0,100)}"), "{\\move(0,0,10,10,0,100)}");
+        assert_eq!(r.text("{\\t(0,100,\\fs10)}"), "{\\t(0,100,\\fs20)}");
+        assert_eq!(r.text("{\\p1}m 0 0 l 10 5{\\p0}"), "{\\p1}m 0 0 l 20 10{\\p0}");
+    }
+
+    #[test]
+    fn script() {
+        let data = "[Script Info]\nPlayResX: 640\nPlayResY: 360\n\n[V4+ Styles]\n\
+            Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+            Style: Default,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1\n\n\
+            [Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+            Dialogue: 0,0:00:00.00,0:00:01.00,Default,,0,5,0,,{\\pos(320,180)}Hi\n";
+        let mut ass = crate::parse_str(data).unwrap();
+        ass.resample(Resolution::new(1280, 720), AspectRatioMode::Stretch);
+        let style = &ass.styles.styles[0];
+        assert_eq!((style.font_size, style.outline, style.margin_v), (40.0, 4.0, 20));
+        let event = &ass.events.events[0].1;
+        assert_eq!((event.margin_l, event.margin_r), (0, 10));
+        assert_eq!(event.text, "{\\pos(640,360)}Hi");
+        assert_eq!(ass.info.resolution.resolved(), (1280, 720));
+    }
+}
@@ -0,0 +1,248 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use std::time::Duration;
+use regex::Regex;
+use crate::structure::event::{Event, EventKey};
+use crate::structure::plain_text::PlainText;
+use crate::structure::{Events, WrapStyle};
+
+/// Event field to search in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Field {
+    /// Visible text, without override tags, comments and drawings.
+    Text,
+    Actor,
+    Effect,
+    Style,
+}
+
+/// Which events and fields to search in.
+#[derive(Debug, Clone)]
+pub struct SearchOptions<'s> {
+    pub fields: Vec<Field>,
+    pub key: Option<EventKey>,
+    pub style: Option<&'s str>,
+    /// Only events overlapping this time range are searched.
+    pub time: Option<Range<Duration>>,
+    /// Defines whether `\n` is a newline or a space in visible text.
+    pub wrap_style: WrapStyle,
+}
+
+impl Default for SearchOptions<'_> {
+    fn default() -> Self {
+        Self {
+            fields: vec![Field::Text],
+            key: None,
+            style: None,
+            time: None,
+            wrap_style: WrapStyle::default(),
+        }
+    }
+}
+
+impl SearchOptions<'_> {
+    pub fn matches(&self, key: EventKey, event: &Event) -> bool {
+        self.key.is_none_or(|x| x == key)
+            && self.style.is_none_or(|x| x == event.style)
+            && self.time.as_ref().is_none_or(|x| event.start < x.end && event.end > x.start)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Match {
+    /// Index in [`Events::events`].
+    pub event: usize,
+    pub field: Field,
+    /// Byte range of the match in the original field value, including tags inside the match.
+    pub range: Range<usize>,
+    /// Matched visible text.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Replacement {
+    pub event: usize,
+    pub field: Field,
+    /// Byte range of the replaced part in the original field value.
+    pub range: Range<usize>,
+    pub original: String,
+    pub replacement: String,
+}
+
+fn field<'e>(event: &'e Event, field: Field) -> &'e str {
+    match field {
+        Field::Text => &event.text,
+        Field::Actor => &event.actor,
+        Field::Effect => &event.effect,
+        Field::Style => &event.style,
+    }
+}
+
+fn field_mut<'e, 'a>(event: &'e mut Event<'a>, field: Field) -> &'e mut Cow<'a, str> {
+    match field {
+        Field::Text => &mut event.text,
+        Field::Actor => &mut event.actor,
+        Field::Effect => &mut event.effect,
+        Field::Style => &mut event.style,
+    }
+}
+
+/// Plain text of the field. Only `Text` has tags, other fields are taken as is.
+fn plain(value: &str, field: Field, wrap_style: WrapStyle) -> Option<PlainText> {
+    match field {
+        Field::Text => Some(PlainText::new(value, wrap_style)),
+        _ => None,
+    }
+}
+
+impl<'a> Events<'a> {
+    /// Finds all matches of `regex` in events selected by `options`.
+    pub fn search(&self, regex: &Regex, options: &SearchOptions) -> Vec<Match> {
+        let mut matches = vec![];
+        for (i, (key, event)) in self.events.iter().enumerate() {
+            if !options.matches(*key, event) {
+                continue;
+            }
+            for &f in &options.fields {
+                let value = field(event, f);
+                let plain = plain(value, f, options.wrap_style);
+                let haystack = plain.as_ref().map(|x| x.text.as_str()).unwrap_or(value);
+                for m in regex.find_iter(haystack) {
+                    let range = match &plain {
+                        Some(plain) => plain.original_range(m.range()),
+                        None => m.range(),
+                    };
+                    matches.push(Match { event: i, field: f, range, text: m.as_str().to_string() });
+                }
+            }
+        }
+        matches
+    }
+
+    /// Replaces all matches of `regex` with `replacement` (which can reference groups like `$1`).
+    ///
+    /// Override tags inside a match are kept: replacement is put in place of the first
+    /// matched piece of text, and other pieces are removed.
+    pub fn replace_all(&mut self, regex: &Regex, replacement: &str, options: &SearchOptions) -> Vec<Replacement> {
+        let mut replacements = vec![];
+        for (i, (key, event)) in self.events.iter_mut().enumerate() {
+            if !options.matches(*key, event) {
+                continue;
+            }
+            for &f in &options.fields {
+                let value = field(event, f);
+                let plain = plain(value, f, options.wrap_style);
+                let haystack = plain.as_ref().map(|x| x.text.as_str()).unwrap_or(value);
+                // Edits as (original range, new text), in order of appearance.
+                let mut edits: Vec<(Range<usize>, String)> = vec![];
+                let mut found = vec![];
+                for caps in regex.captures_iter(haystack) {
+                    let m = caps.get(0).unwrap();
+                    let mut new = String::new();
+                    caps.expand(replacement, &mut new);
+                    let segments = match &plain {
+                        Some(plain) => plain.original_segments(m.range()),
+                        None => vec![m.range()],
+                    };
+                    let range = match &plain {
+                        Some(plain) => plain.original_range(m.range()),
+                        None => m.range(),
+                    };
+                    found.push(Replacement {
+                        event: i,
+                        field: f,
+                        range: range.clone(),
+                        original: value[range.clone()].to_string(),
+                        replacement: new.clone(),
+                    });
+                    match segments.split_first() {
+                        Some((first, rest)) => {
+                            edits.push((first.clone(), new));
+                            edits.extend(rest.iter().map(|x| (x.clone(), String::new())));
+                        }
+                        None => edits.push((range.start..range.start, new)),
+                    }
+                }
+                if edits.is_empty() {
+                    continue;
+                }
+                let mut result = String::with_capacity(value.len());
+                let mut last = 0;
+                for (range, new) in edits {
+                    result.push_str(&value[last..range.start]);
+                    result.push_str(&new);
+                    last = range.end;
+                }
+                result.push_str(&value[last..]);
+                *field_mut(event, f) = Cow::Owned(result);
+                replacements.extend(found);
+            }
+        }
+        replacements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "[Events]
+Dialogue: 0,0:00:00.00,0:00:02.00,Main,Onii-chan,0,0,0,,Onii-chan{\\i1}, Onii-chan!
+Comment: 0,0:00:00.00,0:00:02.00,Main,,0,0,0,,Onii-chan
+Dialogue: 0,0:00:00.00,0:00:02.00,Sign,,0,0,0,,{\\fnOnii-chan}Onii-chan
+Dialogue: 0,0:00:05.00,0:00:06.00,Main,,0,0,0,,On{\\b1}ii-chan
+";
+
+    fn events(options: &SearchOptions) -> Vec<usize> {
+        let ass = crate::parse_str(SCRIPT).unwrap();
+        ass.events.search(&Regex::new("Onii-chan").unwrap(), options).iter().map(|x| x.event).collect()
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(events(&SearchOptions::default()), vec![0, 0, 1, 2, 3]);
+        assert_eq!(events(&SearchOptions { key: Some(EventKey::Dialogue), ..Default::default() }), vec![0, 0, 2, 3]);
+        assert_eq!(events(&SearchOptions { style: Some("Main"), ..Default::default() }), vec![0, 0, 1, 3]);
+        let time = Some(Duration::from_secs(1)..Duration::from_secs(5));
+        assert_eq!(events(&SearchOptions { time, ..Default::default() }), vec![0, 0, 1, 2]);
+        let time = Some(Duration::from_millis(5500)..Duration::from_secs(10));
+        assert_eq!(events(&SearchOptions { time, ..Default::default() }), vec![3]);
+        let fields = vec![Field::Actor, Field::Style];
+        assert_eq!(events(&SearchOptions { fields, ..Default::default() }), vec![0]);
+    }
+
+    #[test]
+    fn search_skips_tags() {
+        let ass = crate::parse_str(SCRIPT).unwrap();
+        let matches = ass.events.search(&Regex::new("Onii-chan").unwrap(), &SearchOptions::default());
+        // Tag arguments are not matched, matches around tags keep them in the range.
+        assert_eq!(matches[3], Match { event: 2, field: Field::Text, range: 14..23, text: "Onii-chan".to_string() });
+        assert_eq!(matches[4], Match { event: 3, field: Field::Text, range: 0..14, text: "Onii-chan".to_string() });
+        assert!(ass.events.search(&Regex::new("i1|fn").unwrap(), &SearchOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn replace_all() {
+        let mut ass = crate::parse_str(SCRIPT).unwrap();
+        let options = SearchOptions { key: Some(EventKey::Dialogue), style: Some("Main"), ..Default::default() };
+        let replacements = ass.events.replace_all(&Regex::new("Onii-(chan)").unwrap(), "Big Brother ($1)", &options);
+        assert_eq!(replacements.len(), 3);
+        assert_eq!(replacements.iter().map(|x| x.event).collect::<Vec<_>>(), vec![0, 0, 3]);
+        assert_eq!(replacements[2].original, "On{\\b1}ii-chan");
+        assert_eq!(replacements[2].replacement, "Big Brother (chan)");
+        let text = |i: usize| ass.events.events[i].1.text.to_string();
+        assert_eq!(text(0), "Big Brother (chan){\\i1}, Big Brother (chan)!");
+        assert_eq!(text(1), "Onii-chan");
+        assert_eq!(text(2), "{\\fnOnii-chan}Onii-chan");
+        // Tags inside a match are kept after the replacement.
+        assert_eq!(text(3), "Big Brother (chan){\\b1}");
+        // Actor is not searched by default.
+        assert_eq!(ass.events.events[0].1.actor, "Onii-chan");
+
+        let options = SearchOptions { fields: vec![Field::Actor], ..Default::default() };
+        let replacements = ass.events.replace_all(&Regex::new("(\\w+)-chan").unwrap(), "$1-san", &options);
+        assert_eq!(replacements.len(), 1);
+        assert_eq!(ass.events.events[0].1.actor, "Onii-san");
+        assert!(ass.events.replace_all(&Regex::new("xyz").unwrap(), "", &SearchOptions::default()).is_empty());
+    }
+}
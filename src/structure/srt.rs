@@ -0,0 +1,156 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::time::Duration;
+use crate::structure::event::{Event, EventKey};
+use crate::structure::plain_text::PlainText;
+use crate::structure::style::Style;
+use crate::structure::{Ass, Events, ScriptInfo, Styles, WrapStyle};
+
+fn format_srt_timestamp(time: Duration) -> String {
+    let ms = time.as_millis();
+    format!("{:02}:{:02}:{:02},{:03}", ms / 3600000, ms / 60000 % 60, ms / 1000 % 60, ms % 1000)
+}
+
+fn parse_srt_timestamp(s: &str) -> Option<Duration> {
+    let (hms, ms) = s.trim().split_once([',', '.'])?;
+    let mut parts = hms.split(':').map(|x| x.trim().parse::<u64>());
+    let (h, m, s) = (parts.next()?.ok()?, parts.next()?.ok()?, parts.next()?.ok()?);
+    let ms: u64 = format!("{:0<3}", ms.trim()).get(..3)?.parse().ok()?;
+    Some(Duration::from_millis(((h * 60 + m) * 60 + s) * 1000 + ms))
+}
+
+/// Converts SRT formatting tags to override tags. Unsupported tags (like `<font>`) are dropped.
+fn srt_text_to_ass(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find('<') {
+        let Some(close) = rest[open..].find('>').map(|x| open + x) else { break };
+        out.push_str(&rest[..open]);
+        let tag = rest[open + 1..close].trim().to_ascii_lowercase();
+        let (name, value) = match tag.strip_prefix('/') {
+            Some(name) => (name, 0),
+            None => (tag.as_str(), 1),
+        };
+        if matches!(name, "i" | "b" | "u" | "s") {
+            write!(out, "{{\\{}{}}}", name, value).unwrap();
+        }
+        rest = &rest[close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+impl Events<'_> {
+    /// Writes `Dialogue` events as SubRip, dropping override tags and drawings.
+    pub fn to_srt(&self, wrap_style: WrapStyle) -> String {
+        let mut events: Vec<&Event> = self
+            .events
+            .iter()
+            .filter(|(key, _)| *key == EventKey::Dialogue)
+            .map(|x| &x.1)
+            .collect();
+        events.sort_by_key(|x| x.start);
+        let mut out = String::new();
+        for (i, event) in events.into_iter().enumerate() {
+            let text = PlainText::new(&event.text, wrap_style).text.replace('\u{a0}', " ");
+            writeln!(
+                out,
+                "{}\n{} --> {}\n{}\n",
+                i + 1,
+                format_srt_timestamp(event.start),
+                format_srt_timestamp(event.end),
+                text.trim_end(),
+            )
+            .unwrap();
+        }
+        out
+    }
+}
+
+/// Parses SubRip subtitles into a script with a single `Default` style.
+pub fn parse_srt(s: &str) -> Ass<'_> {
+    let mut events = vec![];
+    let s = s.strip_prefix('\u{feff}').unwrap_or(s);
+    let mut lines = s.lines().map(str::trim_end).peekable();
+    while lines.peek().is_some() {
+        // Skip blank lines and cue number.
+        let Some(line) = lines.by_ref().find(|x| x.contains("-->")) else { break };
+        let Some((start, end)) = line.split_once("-->") else { continue };
+        // Cue settings may follow end time.
+        let end = end.split_whitespace().next().unwrap_or("");
+        let (Some(start), Some(end)) = (parse_srt_timestamp(start), parse_srt_timestamp(end)) else { continue };
+        let mut text: Vec<&str> = vec![];
+        while let Some(line) = lines.next_if(|x| !x.is_empty()) {
+            text.push(line);
+        }
+        events.push((EventKey::Dialogue, Event {
+            layer: 0,
+            start,
+            end,
+            style: Cow::Borrowed("Default"),
+            actor: Cow::Borrowed(""),
+            margin_l: 0,
+            margin_r: 0,
+            margin_v: 0,
+            effect: Cow::Borrowed(""),
+            text: Cow::Owned(srt_text_to_ass(&text.join("\\N"))),
+        }));
+    }
+    Ass {
+        info: ScriptInfo { version: "v4.00+", ..Default::default() },
+        styles: Styles { styles: vec![Style::default()], ..Default::default() },
+        events: Events { events, ..Default::default() },
+        fonts: vec![],
+        graphics: vec![],
+        other_sections: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_srt_timestamp("01:02:03,456"), Some(Duration::from_millis(3723456)));
+        assert_eq!(parse_srt_timestamp(" 00:00:01.5 "), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_srt_timestamp("00:01"), None);
+        assert_eq!(format_srt_timestamp(Duration::from_millis(3723456)), "01:02:03,456");
+    }
+
+    #[test]
+    fn tags() {
+        assert_eq!(srt_text_to_ass("<i>a</i> <B>b</b> <font color=\"red\">c</font>"), "{\\i1}a{\\i0} {\\b1}b{\\b0} c");
+        assert_eq!(srt_text_to_ass("a < b"), "a < b");
+    }
+
+    #[test]
+    fn parse() {
+        let data = "\u{feff}1\n00:00:01,000 --> 00:00:02,500 X1:0\n<i>Hello</i>\nworld\n\n2\n00:00:03,000 --> 00:00:04,000\n\n3\nbroken --> time\ntext\n";
+        let ass = parse_srt(data);
+        let events = &ass.events.events;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].1.start, Duration::from_millis(1000));
+        assert_eq!(events[0].1.end, Duration::from_millis(2500));
+        assert_eq!(events[0].1.text, "{\\i1}Hello{\\i0}\\Nworld");
+        assert_eq!(events[1].1.text, "");
+        assert_eq!(ass.styles.styles[0].name, "Default");
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = "1\n00:00:01,000 --> 00:00:02,000\nHello\nworld\n\n2\n00:00:00,500 --> 00:00:01,000\nFirst\n\n";
+        let ass = parse_srt(data);
+        let srt = ass.events.to_srt(ass.info.wrap_style);
+        assert_eq!(srt, "1\n00:00:00,500 --> 00:00:01,000\nFirst\n\n2\n00:00:01,000 --> 00:00:02,000\nHello\nworld\n\n");
+    }
+
+    #[test]
+    fn comments_and_tags_are_dropped() {
+        let data = "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+            Comment: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,note\n\
+            Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,{\\pos(1,2)}a\\hb{\\p1}m 0 0 l 1 1{\\p0}\n";
+        let ass = crate::parse_str(data).unwrap();
+        assert_eq!(ass.events.to_srt(WrapStyle::SmartConstant), "1\n00:00:01,000 --> 00:00:02,000\na b\n\n");
+    }
+}
@@ -0,0 +1,453 @@
+use std::borrow::Cow;
+use std::time::Duration;
+use crate::structure::base_types::Color;
+use crate::structure::event::Event;
+use crate::structure::style::{Alignment, Style};
+use crate::structure::tags::{self, Block, OverrideItem, Tag};
+use crate::structure::Styles;
+
+/// Fully resolved look of a piece of event text: style values with override tags applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleState<'a> {
+    pub font_name: Cow<'a, str>,
+    pub font_size: f64,
+    pub primary_color: Color,
+    pub secondary_color: Color,
+    pub outline_color: Color,
+    pub background_color: Color,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikeout: bool,
+    pub scale_x: f64,
+    pub scale_y: f64,
+    pub spacing: f64,
+    pub rotation_x: f64,
+    pub rotation_y: f64,
+    pub rotation_z: f64,
+    pub shear_x: f64,
+    pub shear_y: f64,
+    pub border_x: f64,
+    pub border_y: f64,
+    pub shadow_x: f64,
+    pub shadow_y: f64,
+    pub blur: f64,
+    pub blur_edges: f64,
+    pub alignment: Alignment,
+    pub encoding: u32,
+}
+
+/// Piece of event text sharing the same [`StyleState`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run<'a> {
+    pub text: Cow<'a, str>,
+    /// Drawing scale if the run is a drawing (`\p1` and above).
+    pub drawing: Option<u32>,
+    pub style: StyleState<'a>,
+    /// Karaoke syllable the run belongs to.
+    pub karaoke: Option<Karaoke>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum KaraokeKind {
+    /// `\k`: syllable is highlighted at once when it starts.
+    Instant,
+    /// `\kf` and `\K`: highlight sweeps from left to right.
+    Fill,
+    /// `\ko`: like `\k`, but outline is hidden until highlight.
+    Outline,
+}
+
+/// Karaoke syllable timing, relative to the event start.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Karaoke {
+    pub kind: KaraokeKind,
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+impl Karaoke {
+    /// Highlighted part of the syllable at `time` after the event start, from 0 to 1.
+    pub fn progress(&self, time: Duration) -> f64 {
+        if time < self.start {
+            0.0
+        } else if time >= self.start + self.duration {
+            1.0
+        } else {
+            match self.kind {
+                KaraokeKind::Fill => (time - self.start).as_secs_f64() / self.duration.as_secs_f64(),
+                KaraokeKind::Instant | KaraokeKind::Outline => 1.0,
+            }
+        }
+    }
+}
+
+impl<'a> From<&Style<'a>> for StyleState<'a> {
+    fn from(style: &Style<'a>) -> Self {
+        Self {
+            font_name: Cow::Borrowed(style.font_name),
+            font_size: style.font_size as f64,
+            primary_color: style.primary_color,
+            secondary_color: style.secondary_color,
+            outline_color: style.outline_color,
+            background_color: style.background_color,
+            bold: style.bold,
+            italic: style.italic,
+            underline: style.underline,
+            strikeout: style.strikeout,
+            scale_x: style.scale_x as f64,
+            scale_y: style.scale_y as f64,
+            spacing: style.spacing as f64,
+            rotation_x: 0.0,
+            rotation_y: 0.0,
+            rotation_z: style.angle as f64,
+            shear_x: 0.0,
+            shear_y: 0.0,
+            border_x: style.outline as f64,
+            border_y: style.outline as f64,
+            shadow_x: style.shadow as f64,
+            shadow_y: style.shadow as f64,
+            blur: 0.0,
+            blur_edges: 0.0,
+            alignment: style.alignment,
+            encoding: style.encoding,
+        }
+    }
+}
+
+fn parse_alpha(s: &str) -> Option<u8> {
+    let s = s.trim().trim_start_matches('&').trim_start_matches(['H', 'h']).trim_end_matches('&');
+    u8::from_str_radix(s, 16).ok()
+}
+
+fn line_alignment(tag: &Tag) -> Option<Alignment> {
+    let value = tag.number(0)? as u8;
+    match tag.name {
+        "an" => Alignment::from_numpad(value),
+        "a" => Alignment::from_legacy(value),
+        _ => None,
+    }
+}
+
+fn with_alpha(color: Color, a: u8) -> Color {
+    Color { a, ..color }
+}
+
+fn lerp(from: f64, to: f64, k: f64) -> f64 {
+    from + (to - from) * k
+}
+
+fn lerp_color(from: Color, to: Color, k: f64) -> Color {
+    let channel = |from: u8, to: u8| lerp(from as f64, to as f64, k).round() as u8;
+    Color {
+        a: channel(from.a, to.a),
+        b: channel(from.b, to.b),
+        g: channel(from.g, to.g),
+        r: channel(from.r, to.r),
+    }
+}
+
+impl StyleState<'_> {
+    /// Moves animatable values towards `target` by `k` (from 0 to 1), like `\t` does.
+    pub fn interpolate(&mut self, target: &StyleState<'_>, k: f64) {
+        self.font_size = lerp(self.font_size, target.font_size, k);
+        self.scale_x = lerp(self.scale_x, target.scale_x, k);
+        self.scale_y = lerp(self.scale_y, target.scale_y, k);
+        self.spacing = lerp(self.spacing, target.spacing, k);
+        self.rotation_x = lerp(self.rotation_x, target.rotation_x, k);
+        self.rotation_y = lerp(self.rotation_y, target.rotation_y, k);
+        self.rotation_z = lerp(self.rotation_z, target.rotation_z, k);
+        self.shear_x = lerp(self.shear_x, target.shear_x, k);
+        self.shear_y = lerp(self.shear_y, target.shear_y, k);
+        self.border_x = lerp(self.border_x, target.border_x, k);
+        self.border_y = lerp(self.border_y, target.border_y, k);
+        self.shadow_x = lerp(self.shadow_x, target.shadow_x, k);
+        self.shadow_y = lerp(self.shadow_y, target.shadow_y, k);
+        self.blur = lerp(self.blur, target.blur, k);
+        self.blur_edges = lerp(self.blur_edges, target.blur_edges, k);
+        self.primary_color = lerp_color(self.primary_color, target.primary_color, k);
+        self.secondary_color = lerp_color(self.secondary_color, target.secondary_color, k);
+        self.outline_color = lerp_color(self.outline_color, target.outline_color, k);
+        self.background_color = lerp_color(self.background_color, target.background_color, k);
+    }
+}
+
+impl<'a> StyleState<'a> {
+    /// State of event text before any override tags.
+    pub fn for_event(event: &Event, styles: &'a Styles<'_>) -> Self {
+        match styles.resolve(&event.style) {
+            Some(style) => style.into(),
+            None => (&Style::default()).into(),
+        }
+    }
+
+    /// Applies single override tag. `base` is the style `\r` without arguments resets to.
+    ///
+    /// Animated tags (`\t`, `\move`, `\fad`) are ignored here, see [`Event::at`].
+    pub fn apply(&mut self, tag: &Tag<'a>, base: &Self, styles: &'a Styles<'_>) {
+        let num = tag.number(0);
+        let or = |v: Option<f64>, default: f64| v.unwrap_or(default);
+        let flag = |v: Option<f64>, default: bool| v.map(|x| x == 1.0 || x >= 700.0).unwrap_or(default);
+        // Colors and alphas are set separately, so each keeps the other part of current value.
+        let color = |current: Color, base: Color| {
+            let c = tag.arg(0).and_then(|x| x.parse::<Color>().ok()).unwrap_or(base);
+            with_alpha(c, current.a)
+        };
+        let alpha = |current: Color, base: Color| {
+            with_alpha(current, tag.arg(0).and_then(parse_alpha).unwrap_or(base.a))
+        };
+        match tag.name {
+            "fn" => {
+                self.font_name = match tag.args.first() {
+                    Some(name) if !name.is_empty() && name != "0" => name.clone(),
+                    _ => base.font_name.clone(),
+                }
+            }
+            "fs" => self.font_size = num.filter(|x| *x > 0.0).unwrap_or(base.font_size),
+            "fscx" => self.scale_x = or(num, base.scale_x),
+            "fscy" => self.scale_y = or(num, base.scale_y),
+            "fsp" => self.spacing = or(num, base.spacing),
+            "fe" => self.encoding = num.map(|x| x as u32).unwrap_or(base.encoding),
+            "b" => self.bold = flag(num, base.bold),
+            "i" => self.italic = flag(num, base.italic),
+            "u" => self.underline = flag(num, base.underline),
+            "s" => self.strikeout = flag(num, base.strikeout),
+            "frx" => self.rotation_x = or(num, base.rotation_x),
+            "fry" => self.rotation_y = or(num, base.rotation_y),
+            "frz" | "fr" => self.rotation_z = or(num, base.rotation_z),
+            "fax" => self.shear_x = or(num, base.shear_x),
+            "fay" => self.shear_y = or(num, base.shear_y),
+            "bord" => {
+                self.border_x = or(num, base.border_x);
+                self.border_y = or(num, base.border_y);
+            }
+            "xbord" => self.border_x = or(num, base.border_x),
+            "ybord" => self.border_y = or(num, base.border_y),
+            "shad" => {
+                self.shadow_x = or(num, base.shadow_x);
+                self.shadow_y = or(num, base.shadow_y);
+            }
+            "xshad" => self.shadow_x = or(num, base.shadow_x),
+            "yshad" => self.shadow_y = or(num, base.shadow_y),
+            "blur" => self.blur = or(num, base.blur),
+            "be" => self.blur_edges = or(num, base.blur_edges),
+            "c" | "1c" => self.primary_color = color(self.primary_color, base.primary_color),
+            "2c" => self.secondary_color = color(self.secondary_color, base.secondary_color),
+            "3c" => self.outline_color = color(self.outline_color, base.outline_color),
+            "4c" => self.background_color = color(self.background_color, base.background_color),
+            "1a" => self.primary_color = alpha(self.primary_color, base.primary_color),
+            "2a" => self.secondary_color = alpha(self.secondary_color, base.secondary_color),
+            "3a" => self.outline_color = alpha(self.outline_color, base.outline_color),
+            "4a" => self.background_color = alpha(self.background_color, base.background_color),
+            "alpha" => {
+                self.primary_color = alpha(self.primary_color, base.primary_color);
+                self.secondary_color = alpha(self.secondary_color, base.secondary_color);
+                self.outline_color = alpha(self.outline_color, base.outline_color);
+                self.background_color = alpha(self.background_color, base.background_color);
+            }
+            "an" | "a" => {
+                if let Some(a) = line_alignment(tag) {
+                    self.alignment = a
+                }
+            }
+            "r" => {
+                // Alignment is a property of the whole line, so `\r` keeps it.
+                let alignment = self.alignment;
+                *self = match tag.arg(0).and_then(|x| styles.get(x)) {
+                    Some(style) => style.into(),
+                    None => base.clone(),
+                };
+                self.alignment = alignment;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'a> Event<'a> {
+    /// Splits text into runs with effective style of each of them.
+    pub fn runs<'b>(&'b self, styles: &'b Styles<'_>) -> Vec<Run<'b>> {
+        collect_runs(self, styles, None)
+    }
+}
+
+/// Builds runs of event. With `time` (relative to the event start) `\t` tags are evaluated too.
+pub(crate) fn collect_runs<'b>(event: &'b Event, styles: &'b Styles<'_>, time: Option<Duration>) -> Vec<Run<'b>> {
+    let blocks = tags::parse(&event.text);
+    let mut base = StyleState::for_event(event, styles);
+    // Only the first alignment tag of the line has an effect.
+    let alignment = blocks
+        .iter()
+        .flat_map(|x| match x {
+            Block::Override(items) => items.as_slice(),
+            _ => &[],
+        })
+        .find_map(|x| match x {
+            OverrideItem::Tag(tag) => line_alignment(tag),
+            _ => None,
+        });
+    if let Some(alignment) = alignment {
+        base.alignment = alignment;
+    }
+    let duration = event.end.saturating_sub(event.start);
+    let mut state = base.clone();
+    let mut karaoke: Option<Karaoke> = None;
+    let mut karaoke_end = Duration::ZERO;
+    let mut runs = vec![];
+    for block in blocks {
+        match block {
+            Block::Override(items) => {
+                for item in &items {
+                    let OverrideItem::Tag(tag) = item else { continue };
+                    match tag.name {
+                        "an" | "a" => {}
+                        "t" => {
+                            if let Some(time) = time {
+                                let mut target = state.clone();
+                                for nested in tag.nested() {
+                                    if let OverrideItem::Tag(nested) = nested {
+                                        target.apply(&nested, &base, styles);
+                                    }
+                                }
+                                state.interpolate(&target, transform_progress(tag, time, duration));
+                            }
+                        }
+                        "k" | "K" | "kf" | "ko" => {
+                            let kind = match tag.name {
+                                "k" => KaraokeKind::Instant,
+                                "ko" => KaraokeKind::Outline,
+                                _ => KaraokeKind::Fill,
+                            };
+                            let length = Duration::from_millis(tag.number(0).unwrap_or(0.0).max(0.0) as u64 * 10);
+                            karaoke = Some(Karaoke { kind, start: karaoke_end, duration: length });
+                            karaoke_end += length;
+                        }
+                        _ => state.apply(tag, &base, styles),
+                    }
+                }
+            }
+            Block::Text(text) => runs.push(Run { text, drawing: None, style: state.clone(), karaoke }),
+            Block::Drawing { scale, commands } => runs.push(Run {
+                text: commands,
+                drawing: Some(scale),
+                style: state.clone(),
+                karaoke,
+            }),
+        }
+    }
+    runs
+}
+
+/// Progress of `\t(t1,t2,accel,...)` at `time` after the event start.
+pub(crate) fn transform_progress(tag: &Tag, time: Duration, duration: Duration) -> f64 {
+    let numbers: Vec<f64> = tag.args[..tag.args.len().saturating_sub(1)]
+        .iter()
+        .filter_map(|x| tags::parse_number(x))
+        .collect();
+    let duration = duration.as_millis() as f64;
+    let (t1, t2, accel) = match numbers[..] {
+        [accel] => (0.0, duration, accel),
+        [t1, t2] => (t1, t2, 1.0),
+        [t1, t2, accel, ..] => (t1, t2, accel),
+        _ => (0.0, duration, 1.0),
+    };
+    let t = time.as_millis() as f64;
+    if t <= t1 {
+        0.0
+    } else if t >= t2 || t2 <= t1 {
+        1.0
+    } else {
+        ((t - t1) / (t2 - t1)).powf(accel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "[V4+ Styles]
+Style: Default,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1
+Style: Sign,Verdana,30,&H000000FF,&H000000FF,&H00000000,&H00000000,-1,0,0,0,100,100,0,0,1,3,0,8,10,10,10,1
+
+[Events]
+";
+
+    fn runs<T>(text: &str, check: impl FnOnce(&[Run], &Styles) -> T) -> T {
+        let data = format!("{}Dialogue: 0,0:00:00.00,0:00:02.00,Default,,0,0,0,,{}\n", SCRIPT, text);
+        let ass = crate::parse_str(&data).unwrap();
+        let runs = ass.events.events[0].1.runs(&ass.styles);
+        check(&runs, &ass.styles)
+    }
+
+    #[test]
+    fn mixed_tags() {
+        runs("a{\\b1\\fs30\\c&H00FF00&\\1a&H80&}b{\\fnTimes\\bord4\\xbord1}c", |runs, _| {
+            let text: Vec<_> = runs.iter().map(|x| x.text.as_ref()).collect();
+            assert_eq!(text, vec!["a", "b", "c"]);
+            assert!(!runs[0].style.bold);
+            assert_eq!(runs[0].style.font_size, 20.0);
+            let b = &runs[1].style;
+            assert!(b.bold);
+            assert_eq!(b.font_size, 30.0);
+            // Color and alpha are set separately.
+            assert_eq!(b.primary_color, Color { a: 0x80, b: 0, g: 0xff, r: 0 });
+            let c = &runs[2].style;
+            assert_eq!((c.font_name.as_ref(), c.font_size), ("Times", 30.0));
+            assert_eq!((c.border_x, c.border_y), (1.0, 4.0));
+        });
+    }
+
+    #[test]
+    fn reset() {
+        runs("{\\b1\\fs40}a{\\r}b{\\rSign}c{\\rMissing}d", |runs, styles| {
+            let default = StyleState::from(styles.get("Default").unwrap());
+            assert_eq!(runs[0].style.font_size, 40.0);
+            assert_eq!(runs[1].style, default);
+            let sign = &runs[2].style;
+            assert_eq!((sign.font_name.as_ref(), sign.font_size, sign.bold), ("Verdana", 30.0, true));
+            // `\r` doesn't change alignment of the line.
+            assert_eq!(sign.alignment, Alignment::BottomCenter);
+            // Unknown style resets to the event style.
+            assert_eq!(runs[3].style, default);
+        });
+    }
+
+    #[test]
+    fn alignment() {
+        let alignment = |text: &str| runs(text, |runs, _| runs.iter().map(|x| x.style.alignment).collect::<Vec<_>>());
+        assert_eq!(alignment("a"), vec![Alignment::BottomCenter]);
+        // Only the first alignment tag has an effect, and it applies to the whole line.
+        assert_eq!(alignment("a{\\an7}b{\\an3}c"), vec![Alignment::TopLeft; 3]);
+        assert_eq!(alignment("{\\a5}a"), vec![Alignment::TopLeft]);
+        assert_eq!(alignment("{\\a6}a"), vec![Alignment::TopCenter]);
+        assert_eq!(alignment("{\\a10}a"), vec![Alignment::CenterCenter]);
+        assert_eq!(alignment("{\\a3}a"), vec![Alignment::BottomRight]);
+        // Invalid values are ignored.
+        assert_eq!(alignment("{\\a4\\an0}a"), vec![Alignment::BottomCenter]);
+    }
+
+    #[test]
+    fn karaoke() {
+        runs("{\\k10}ka{\\kf50}ra{\\ko5}o{\\K20}ke", |runs, _| {
+            let karaoke: Vec<_> = runs.iter().map(|x| x.karaoke.unwrap()).collect();
+            let ms = Duration::from_millis;
+            assert_eq!(karaoke[0], Karaoke { kind: KaraokeKind::Instant, start: ms(0), duration: ms(100) });
+            assert_eq!(karaoke[1], Karaoke { kind: KaraokeKind::Fill, start: ms(100), duration: ms(500) });
+            assert_eq!(karaoke[2], Karaoke { kind: KaraokeKind::Outline, start: ms(600), duration: ms(50) });
+            assert_eq!(karaoke[3], Karaoke { kind: KaraokeKind::Fill, start: ms(650), duration: ms(200) });
+            assert_eq!(karaoke[0].progress(ms(50)), 1.0);
+            assert_eq!(karaoke[1].progress(ms(50)), 0.0);
+            assert_eq!(karaoke[1].progress(ms(225)), 0.25);
+            assert_eq!(karaoke[1].progress(ms(600)), 1.0);
+        });
+        assert_eq!(runs("a{\\k10}b", |runs, _| runs[0].karaoke), None);
+    }
+
+    #[test]
+    fn drawing() {
+        runs("a{\\p2}m 0 0 l 1 1{\\p0}b", |runs, _| {
+            let drawings: Vec<_> = runs.iter().map(|x| (x.text.as_ref(), x.drawing)).collect();
+            assert_eq!(drawings, vec![("a", None), ("m 0 0 l 1 1", Some(2)), ("b", None)]);
+        });
+    }
+}
@@ -1,111 +1,175 @@
 use std::str::FromStr;
-use asai_macro::FromLine;
+use asai_macro::{AssEnum, FromLine, ToLine};
 use crate::structure::InvalidValue;
 use super::base_types::*;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(AssEnum, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum BorderStyle {
+    #[value(1)]
     Outline,
+    #[value(3)]
     Opaque,
 }
 
-impl<'a> TryFrom<LineField<'a>> for BorderStyle {
-    type Error = InvalidValue;
-
-    fn try_from(value: LineField<'a>) -> Result<Self, Self::Error> {
-        let i: u8 = value.value().parse()?;
-        match i {
-            1 => Ok(Self::Outline),
-            3 => Ok(Self::Opaque),
-            _ => Err(InvalidValue)
+impl BorderStyle {
+    pub fn value(&self) -> u8 {
+        match self {
+            Self::Outline => 1,
+            Self::Opaque => 3,
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+/// Alignment of `Style:` lines, written as numpad-like `\an` value.
+#[derive(AssEnum, Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Alignment {
+    #[value(7)]
     TopLeft,
+    #[value(8)]
     TopCenter,
+    #[value(9)]
     TopRight,
+    #[value(4)]
     CenterLeft,
+    #[value(5)]
     CenterCenter,
+    #[value(6)]
     CenterRight,
+    #[value(1)]
     BottomLeft,
+    #[value(2)]
     BottomCenter,
+    #[value(3)]
     BottomRight,
 }
 
-impl<'a> TryFrom<LineField<'a>> for Alignment {
-    type Error = InvalidValue;
+impl Alignment {
+    /// Alignment from numpad-like `\an` value.
+    pub fn from_numpad(i: u8) -> Option<Self> {
+        match i {
+            1 => Some(Self::BottomLeft),
+            2 => Some(Self::BottomCenter),
+            3 => Some(Self::BottomRight),
+            4 => Some(Self::CenterLeft),
+            5 => Some(Self::CenterCenter),
+            6 => Some(Self::CenterRight),
+            7 => Some(Self::TopLeft),
+            8 => Some(Self::TopCenter),
+            9 => Some(Self::TopRight),
+            _ => None
+        }
+    }
 
-    fn try_from(value: LineField<'a>) -> Result<Self, Self::Error> {
-        let i: u8 = value.value().parse()?;
+    /// Alignment from legacy SSA `\a` value.
+    pub fn from_legacy(i: u8) -> Option<Self> {
         match i {
-            1 => {Ok(Self::BottomLeft)}
-            2 => {Ok(Self::BottomCenter)}
-            3 => {Ok(Self::BottomRight)}
-            4 => {Ok(Self::CenterLeft)}
-            5 => {Ok(Self::CenterCenter)}
-            6 => {Ok(Self::CenterRight)}
-            7 => {Ok(Self::TopLeft)}
-            8 => {Ok(Self::TopCenter)}
-            9 => {Ok(Self::TopRight)}
-            _ => Err(InvalidValue)
+            1..=3 => Self::from_numpad(i),
+            5..=7 => Self::from_numpad(i + 2),
+            9..=11 => Self::from_numpad(i - 5),
+            _ => None
+        }
+    }
+
+    pub fn numpad(&self) -> u8 {
+        match self {
+            Self::BottomLeft => 1,
+            Self::BottomCenter => 2,
+            Self::BottomRight => 3,
+            Self::CenterLeft => 4,
+            Self::CenterCenter => 5,
+            Self::CenterRight => 6,
+            Self::TopLeft => 7,
+            Self::TopCenter => 8,
+            Self::TopRight => 9,
         }
     }
 }
 
-#[derive(FromLine, Debug, Clone, PartialEq)]
+#[derive(FromLine, ToLine, Debug, Clone, PartialEq)]
 pub struct Style<'a> {
     #[name("Name")]
-    name: LineField<'a>,
+    pub name: &'a str,
     #[name("Fontname")]
-    font_name: LineField<'a>,
+    pub font_name: &'a str,
     #[name("Fontsize")]
-    font_size: u32,
+    pub font_size: f32,
     #[name("PrimaryColour")]
-    primary_color: Color,
+    pub primary_color: Color,
     #[name("SecondaryColour")]
-    secondary_color: Color,
-    #[name("OutlineColor")]
-    outline_color: Color,
+    pub secondary_color: Color,
+    #[name("OutlineColour")]
+    pub outline_color: Color,
     #[name("BackColour")]
-    background_color: Color,
+    pub background_color: Color,
     #[name("Bold")]
-    bold: bool,
+    pub bold: bool,
     #[name("Italic")]
-    italic: bool,
+    pub italic: bool,
     #[name("Underline")]
-    underline: bool,
-    #[name("Strikeout")]
-    strikeout: bool,
+    pub underline: bool,
+    #[name("StrikeOut")]
+    pub strikeout: bool,
     #[name("ScaleX")]
-    scale_x: f32,
+    pub scale_x: f32,
     #[name("ScaleY")]
-    scale_y: f32,
+    pub scale_y: f32,
     #[name("Spacing")]
-    spacing: u32,
+    pub spacing: f32,
     #[name("Angle")]
-    angle: f32,
+    pub angle: f32,
     #[name("BorderStyle")]
-    border_style: BorderStyle,
+    pub border_style: BorderStyle,
     #[name("Outline")]
-    outline: f32,
+    pub outline: f32,
     #[name("Shadow")]
-    shadow: f32,
+    pub shadow: f32,
     #[name("Alignment")]
-    alignment: Alignment,
+    pub alignment: Alignment,
     #[name("MarginL")]
-    margin_l: u32,
+    pub margin_l: u32,
     #[name("MarginR")]
-    margin_r: u32,
+    pub margin_r: u32,
     #[name("MarginV")]
-    margin_v: u32,
+    pub margin_v: u32,
     #[name("Encoding")]
-    encoding: u32,
+    #[default(1)]
+    pub encoding: u32,
 }
 
 
+impl Default for Style<'_> {
+    /// Style Aegisub creates for new scripts.
+    fn default() -> Self {
+        let white = Color { a: 0, b: 255, g: 255, r: 255 };
+        let black = Color { a: 0, b: 0, g: 0, r: 0 };
+        Self {
+            name: "Default",
+            font_name: "Arial",
+            font_size: 48.0,
+            primary_color: white,
+            secondary_color: Color { a: 0, b: 0, g: 0, r: 255 },
+            outline_color: black,
+            background_color: black,
+            bold: false,
+            italic: false,
+            underline: false,
+            strikeout: false,
+            scale_x: 100.0,
+            scale_y: 100.0,
+            spacing: 0.0,
+            angle: 0.0,
+            border_style: BorderStyle::Outline,
+            outline: 2.0,
+            shadow: 2.0,
+            alignment: Alignment::BottomCenter,
+            margin_l: 10,
+            margin_r: 10,
+            margin_v: 10,
+            encoding: 1,
+        }
+    }
+}
+
 pub enum StyleKey {
     Style,
 }
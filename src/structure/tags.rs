@@ -0,0 +1,337 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter, Write};
+use std::ops::Range;
+
+/// Names of override tags known to VSFilter/libass.
+/// Ordered so that longer names come before their prefixes (`fscx` before `fs`).
+pub const KNOWN_TAGS: &[&str] = &[
+    "xbord", "ybord", "xshad", "yshad", "iclip", "alpha", "blur", "bord", "clip", "fade", "fscx",
+    "fscy", "move", "shad", "fax", "fay", "frx", "fry", "frz", "fad", "fsp", "org", "pbo", "pos",
+    "1c", "2c", "3c", "4c", "1a", "2a", "3a", "4a", "an", "be", "fe", "fn", "fr", "fs", "kf", "ko",
+    "a", "b", "c", "i", "k", "K", "p", "q", "r", "s", "t", "u",
+];
+
+/// Part of event text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block<'a> {
+    /// Plain text. Can contain `\N`, `\n` and `\h` escapes.
+    Text(Cow<'a, str>),
+    /// Content of `{...}`.
+    Override(Vec<OverrideItem<'a>>),
+    /// Text in drawing mode (`\p1` and above), with the drawing scale.
+    Drawing { scale: u32, commands: Cow<'a, str> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OverrideItem<'a> {
+    Tag(Tag<'a>),
+    /// Anything in override block which is not a tag, like `{TL note}`.
+    Comment(&'a str),
+}
+
+/// Single override tag, like `\fs40` or `\pos(10,20)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag<'a> {
+    pub name: &'a str,
+    pub args: Vec<Cow<'a, str>>,
+    /// Whether arguments were written in parentheses.
+    pub parens: bool,
+}
+
+impl<'a> Tag<'a> {
+    pub fn new(name: &'a str, args: Vec<Cow<'a, str>>, parens: bool) -> Self {
+        Self { name, args, parens }
+    }
+
+    pub fn is_known(&self) -> bool {
+        KNOWN_TAGS.contains(&self.name)
+    }
+
+    pub fn arg(&self, i: usize) -> Option<&str> {
+        self.args.get(i).map(|x| x.as_ref())
+    }
+
+    /// Argument parsed like VSFilter does: leading number, the rest is ignored.
+    pub fn number(&self, i: usize) -> Option<f64> {
+        self.arg(i).and_then(parse_number)
+    }
+
+    /// Tags nested into `\t`. Empty for other tags.
+    pub fn nested(&self) -> Vec<OverrideItem<'_>> {
+        if self.name != "t" {
+            return vec![];
+        }
+        self.args
+            .last()
+            .filter(|x| x.trim_start().starts_with('\\'))
+            .map(|x| parse_override(x))
+            .unwrap_or_default()
+    }
+}
+
+impl Display for Tag<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\\{}", self.name)?;
+        if self.parens {
+            f.write_char('(')?;
+            for (i, arg) in self.args.iter().enumerate() {
+                if i != 0 {
+                    f.write_char(',')?;
+                }
+                f.write_str(arg)?;
+            }
+            f.write_char(')')
+        } else {
+            self.args.iter().try_for_each(|x| f.write_str(x))
+        }
+    }
+}
+
+impl Display for OverrideItem<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverrideItem::Tag(tag) => tag.fmt(f),
+            OverrideItem::Comment(comment) => f.write_str(comment),
+        }
+    }
+}
+
+impl Display for Block<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Block::Text(text) => f.write_str(text),
+            Block::Drawing { commands, .. } => f.write_str(commands),
+            Block::Override(items) => {
+                f.write_char('{')?;
+                items.iter().try_for_each(|x| x.fmt(f))?;
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+/// Splits event text into blocks.
+pub fn parse(text: &str) -> Vec<Block<'_>> {
+    parse_spans(text).into_iter().map(|x| x.1).collect()
+}
+
+/// Like [`parse`], but also returns byte range of each block in `text`.
+pub fn parse_spans(text: &str) -> Vec<(Range<usize>, Block<'_>)> {
+    let mut blocks = vec![];
+    let mut drawing_scale = 0;
+    let mut pos = 0;
+    while pos < text.len() {
+        let rest = &text[pos..];
+        let open = rest.find('{');
+        let close = open.and_then(|o| rest[o..].find('}').map(|c| o + c));
+        match (open, close) {
+            (Some(o), Some(c)) => {
+                push_text(&mut blocks, text, pos..pos + o, drawing_scale);
+                let items = parse_override(&rest[o + 1..c]);
+                for item in &items {
+                    if let OverrideItem::Tag(tag) = item {
+                        match tag.name {
+                            "p" => drawing_scale = tag.number(0).map(|x| x.max(0.0) as u32).unwrap_or(0),
+                            "r" => drawing_scale = 0,
+                            _ => {}
+                        }
+                    }
+                }
+                blocks.push((pos + o..pos + c + 1, Block::Override(items)));
+                pos += c + 1;
+            }
+            _ => {
+                push_text(&mut blocks, text, pos..text.len(), drawing_scale);
+                pos = text.len();
+            }
+        }
+    }
+    blocks
+}
+
+fn push_text<'a>(blocks: &mut Vec<(Range<usize>, Block<'a>)>, text: &'a str, range: Range<usize>, scale: u32) {
+    if range.is_empty() {
+        return;
+    }
+    let s = &text[range.clone()];
+    blocks.push((range, if scale > 0 {
+        Block::Drawing { scale, commands: s.into() }
+    } else {
+        Block::Text(s.into())
+    }));
+}
+
+/// Writes blocks back into event text.
+pub fn to_text(blocks: &[Block]) -> String {
+    let mut s = String::new();
+    for block in blocks {
+        write!(s, "{}", block).unwrap();
+    }
+    s
+}
+
+/// Parses content of override block (without braces).
+pub fn parse_override(s: &str) -> Vec<OverrideItem<'_>> {
+    let mut items = vec![];
+    let mut rest = s;
+    while !rest.is_empty() {
+        let Some(body) = rest.strip_prefix('\\') else {
+            let end = rest.find('\\').unwrap_or(rest.len());
+            items.push(OverrideItem::Comment(&rest[..end]));
+            rest = &rest[end..];
+            continue;
+        };
+        let name = tag_name(body);
+        let after = &body[name.len()..];
+        if after.trim_start().starts_with('(') {
+            let after = after.trim_start();
+            let end = closing_paren(after);
+            let inner = &after[1..end];
+            let args = if name == "t" { split_transform_args(inner) } else { split_args(inner) };
+            items.push(OverrideItem::Tag(Tag::new(name, args, true)));
+            rest = if end < after.len() { &after[end + 1..] } else { "" };
+        } else {
+            let end = after.find('\\').unwrap_or(after.len());
+            let value = after[..end].trim_end();
+            let args = if value.is_empty() { vec![] } else { vec![Cow::Borrowed(value)] };
+            items.push(OverrideItem::Tag(Tag::new(name, args, false)));
+            rest = &after[end..];
+        }
+    }
+    items
+}
+
+fn tag_name(body: &str) -> &str {
+    if let Some(name) = KNOWN_TAGS.iter().find(|x| body.starts_with(*x)) {
+        return &body[..name.len()];
+    }
+    let end = body
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(body.len());
+    &body[..end]
+}
+
+/// Index of `)` matching `(` at the start of `s`, or `s.len()` if it is not closed.
+fn closing_paren(s: &str) -> usize {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+fn split_args(s: &str) -> Vec<Cow<'_, str>> {
+    if s.trim().is_empty() {
+        return vec![];
+    }
+    s.split(',').map(|x| Cow::Borrowed(x.trim())).collect()
+}
+
+/// `\t` arguments: up to three numbers followed by tags, which may contain commas.
+fn split_transform_args(s: &str) -> Vec<Cow<'_, str>> {
+    let mut args = vec![];
+    let mut rest = s;
+    loop {
+        if rest.trim_start().starts_with('\\') {
+            args.push(Cow::Borrowed(rest.trim()));
+            break;
+        }
+        match rest.find(',') {
+            Some(i) => {
+                args.push(Cow::Borrowed(rest[..i].trim()));
+                rest = &rest[i + 1..];
+            }
+            None => {
+                if !rest.trim().is_empty() {
+                    args.push(Cow::Borrowed(rest.trim()));
+                }
+                break;
+            }
+        }
+    }
+    args
+}
+
+/// Parses leading number of the string, ignoring the rest like `strtod` does.
+pub fn parse_number(s: &str) -> Option<f64> {
+    let s = s.trim_start();
+    let mut end = 0;
+    let mut seen_digit = false;
+    let mut seen_dot = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '+' | '-' if i == 0 => {}
+            '0'..='9' => seen_digit = true,
+            '.' if !seen_dot => seen_dot = true,
+            _ => break,
+        }
+        end = i + c.len_utf8();
+    }
+    if !seen_digit {
+        return None;
+    }
+    s[..end].parse().ok()
+}
+
+/// Formats number like Aegisub does: at most three decimal places, no trailing zeros.
+pub fn format_number(v: f64) -> String {
+    let s = format!("{:.3}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    match s {
+        "-0" | "" => "0".to_string(),
+        s => s.to_string(),
+    }
+}
+
+/// Applies `f` to every point of drawing commands (`m 0 0 l 10 0 ...`).
+pub fn transform_drawing(commands: &str, mut f: impl FnMut(f64, f64) -> (f64, f64)) -> String {
+    let mut out: Vec<String> = vec![];
+    let mut pending: Option<f64> = None;
+    let mut tokens = vec![];
+    for word in commands.split_whitespace() {
+        let mut start = 0;
+        for (i, c) in word.char_indices() {
+            if c.is_ascii_alphabetic() {
+                if start < i {
+                    tokens.push(&word[start..i]);
+                }
+                tokens.push(&word[i..i + 1]);
+                start = i + 1;
+            }
+        }
+        if start < word.len() {
+            tokens.push(&word[start..]);
+        }
+    }
+    for token in tokens {
+        match parse_number(token) {
+            Some(v) => match pending.take() {
+                Some(x) => {
+                    let (x, y) = f(x, v);
+                    out.push(format_number(x));
+                    out.push(format_number(y));
+                }
+                None => pending = Some(v),
+            },
+            None => {
+                if let Some(x) = pending.take() {
+                    out.push(format_number(x));
+                }
+                out.push(token.to_string());
+            }
+        }
+    }
+    if let Some(x) = pending {
+        out.push(format_number(x));
+    }
+    out.join(" ")
+}
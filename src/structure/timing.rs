@@ -0,0 +1,221 @@
+use std::time::Duration;
+use crate::structure::event::{Event, EventKey};
+use crate::structure::Events;
+
+/// Settings of [`Events::process_timing`], modelled after Aegisub's timing post-processor.
+/// Steps run in order: lead-in/out, linking, keyframe snapping. Zero or empty values disable a step.
+#[derive(Debug, Clone)]
+pub struct TimingOptions<'s> {
+    /// Only events of these styles are processed, all if empty.
+    pub styles: Vec<&'s str>,
+    pub keys: Vec<EventKey>,
+    /// Moves start back, but not before the end of the previous line.
+    pub lead_in: Duration,
+    /// Moves end forward, but not after the start of the next line.
+    pub lead_out: Duration,
+    /// Gaps between consecutive lines up to this long are closed.
+    pub link_threshold: Duration,
+    /// Where linked lines meet inside of the gap: 0 is the end of the first line, 1 is the start of the second.
+    pub link_bias: f64,
+    /// Sorted times of scene changes.
+    pub keyframes: Vec<Duration>,
+    /// Start this much earlier than a keyframe is moved to it.
+    pub start_before: Duration,
+    /// Start this much later than a keyframe is moved to it.
+    pub start_after: Duration,
+    pub end_before: Duration,
+    pub end_after: Duration,
+}
+
+impl Default for TimingOptions<'_> {
+    /// Keyframe windows are Aegisub defaults in frames at 23.976 fps.
+    fn default() -> Self {
+        Self {
+            styles: vec![],
+            keys: vec![EventKey::Dialogue],
+            lead_in: Duration::ZERO,
+            lead_out: Duration::ZERO,
+            link_threshold: Duration::ZERO,
+            link_bias: 0.5,
+            keyframes: vec![],
+            start_before: Duration::from_millis(210),
+            start_after: Duration::from_millis(170),
+            end_before: Duration::from_millis(210),
+            end_after: Duration::from_millis(500),
+        }
+    }
+}
+
+impl TimingOptions<'_> {
+    pub fn matches(&self, key: EventKey, event: &Event) -> bool {
+        self.keys.contains(&key) && (self.styles.is_empty() || self.styles.iter().any(|x| *x == event.style))
+    }
+
+    /// Keyframe nearest to `time` from `time - after` to `time + before`.
+    fn keyframe(&self, time: Duration, before: Duration, after: Duration) -> Option<Duration> {
+        let low = time.saturating_sub(after);
+        let high = time + before;
+        let first = self.keyframes.partition_point(|x| *x < low);
+        self.keyframes[first..]
+            .iter()
+            .take_while(|x| **x <= high)
+            .min_by_key(|x| x.abs_diff(time))
+            .copied()
+    }
+}
+
+impl Events<'_> {
+    /// Adjusts times of matching events. Returns number of changed events.
+    ///
+    /// Only lines of the same layer are neighbours for lead-in/out and linking.
+    pub fn process_timing(&mut self, options: &TimingOptions) -> usize {
+        let mut indices: Vec<usize> = (0..self.events.len())
+            .filter(|i| options.matches(self.events[*i].0, &self.events[*i].1))
+            .collect();
+        indices.sort_by_key(|i| (self.events[*i].1.layer, self.events[*i].1.start));
+        let same_layer = |a: &usize, b: &usize| self.events[*a].1.layer == self.events[*b].1.layer;
+        let layers: Vec<&[usize]> = indices.chunk_by(same_layer).collect();
+        let mut changed = 0;
+        for layer in layers {
+            let original: Vec<(Duration, Duration)> =
+                layer.iter().map(|i| (self.events[*i].1.start, self.events[*i].1.end)).collect();
+            let times = adjust(&original, options);
+            for (n, i) in layer.iter().enumerate() {
+                let event = &mut self.events[*i].1;
+                if (event.start, event.end) != times[n] {
+                    (event.start, event.end) = times[n];
+                    changed += 1;
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// New times of lines sorted by start.
+fn adjust(original: &[(Duration, Duration)], options: &TimingOptions) -> Vec<(Duration, Duration)> {
+    let mut times = original.to_vec();
+
+    if !options.lead_in.is_zero() || !options.lead_out.is_zero() {
+        for n in 0..times.len() {
+            let start = original[n].0;
+            times[n].0 = start.saturating_sub(options.lead_in);
+            if let Some(&(_, prev_end)) = n.checked_sub(1).map(|x| &original[x]) {
+                if prev_end <= start {
+                    times[n].0 = times[n].0.max(prev_end);
+                }
+            }
+        }
+        // Lead-out stops at lead-in of the next line, so they don't overlap.
+        for n in 0..times.len() {
+            let end = original[n].1;
+            times[n].1 = end + options.lead_out;
+            if let Some(&(next_start, _)) = original.get(n + 1) {
+                if next_start >= end {
+                    times[n].1 = times[n].1.min(times[n + 1].0.max(end));
+                }
+            }
+        }
+    }
+
+    if !options.link_threshold.is_zero() {
+        for n in 1..times.len() {
+            let end = times[n - 1].1;
+            let start = times[n].0;
+            if start > end && start - end <= options.link_threshold {
+                let meet = end + (start - end).mul_f64(options.link_bias.clamp(0.0, 1.0));
+                times[n - 1].1 = meet;
+                times[n].0 = meet;
+            }
+        }
+    }
+
+    if !options.keyframes.is_empty() {
+        for (start, end) in &mut times {
+            let new_start = options.keyframe(*start, options.start_before, options.start_after).unwrap_or(*start);
+            let new_end = options.keyframe(*end, options.end_before, options.end_after).unwrap_or(*end);
+            // Don't snap a short line to nothing.
+            if new_start < new_end {
+                (*start, *end) = (new_start, new_end);
+            }
+        }
+    }
+    times
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Events from `(key, layer, style, start, end)` in seconds.
+    fn events(lines: &[(&str, u32, &str, f64, f64)]) -> Events<'static> {
+        let mut data = "[Events]\n".to_string();
+        for (key, layer, style, start, end) in lines {
+            let time = |x: f64| crate::structure::event::format_timestamp(Duration::from_secs_f64(x));
+            data.push_str(&format!("{}: {},{},{},{},,0,0,0,,a\n", key, layer, time(*start), time(*end), style));
+        }
+        let ass = crate::parse_str(&data).unwrap();
+        let events = ass.events.events.into_iter().map(|(k, e)| (k, e.into_owned())).collect();
+        Events { events, ..Default::default() }
+    }
+
+    fn times(events: &Events) -> Vec<(u128, u128)> {
+        events.events.iter().map(|(_, e)| (e.start.as_millis(), e.end.as_millis())).collect()
+    }
+
+    #[test]
+    fn lead_in_and_out() {
+        let mut events = events(&[
+            ("Dialogue", 0, "Default", 0.1, 2.0),
+            ("Dialogue", 0, "Default", 2.1, 3.0),
+            ("Dialogue", 0, "Default", 5.0, 6.0),
+            ("Comment", 0, "Default", 3.5, 4.0),
+            ("Dialogue", 0, "Sign", 3.5, 4.0),
+        ]);
+        let options = TimingOptions {
+            lead_in: Duration::from_millis(200),
+            lead_out: Duration::from_millis(500),
+            styles: vec!["Default"],
+            ..Default::default()
+        };
+        assert_eq!(events.process_timing(&options), 3);
+        // Lead-in stops at the end of the previous line, lead-out at the start of the next one.
+        assert_eq!(times(&events), vec![(0, 2000), (2000, 3500), (4800, 6500), (3500, 4000), (3500, 4000)]);
+    }
+
+    #[test]
+    fn linking() {
+        let lines = [
+            ("Dialogue", 0, "Default", 1.0, 2.0),
+            ("Dialogue", 0, "Default", 2.4, 3.0),
+            ("Dialogue", 0, "Default", 4.0, 5.0),
+            // Lines of other layers are not neighbours.
+            ("Dialogue", 1, "Default", 2.2, 2.3),
+        ];
+        let link = |bias: f64| {
+            let mut events = events(&lines);
+            let link_threshold = Duration::from_millis(500);
+            let options = TimingOptions { link_threshold, link_bias: bias, ..Default::default() };
+            events.process_timing(&options);
+            times(&events)
+        };
+        assert_eq!(link(0.5), vec![(1000, 2200), (2200, 3000), (4000, 5000), (2200, 2300)]);
+        assert_eq!(link(0.0), vec![(1000, 2000), (2000, 3000), (4000, 5000), (2200, 2300)]);
+        assert_eq!(link(1.0)[..2], [(1000, 2400), (2400, 3000)]);
+    }
+
+    #[test]
+    fn keyframes() {
+        let mut events = events(&[
+            ("Dialogue", 0, "Default", 1.0, 3.0),
+            ("Dialogue", 1, "Default", 1.25, 2.0),
+            ("Dialogue", 2, "Default", 5.0, 6.0),
+            ("Dialogue", 3, "Default", 9.95, 10.05),
+        ]);
+        let keyframes = [1.1, 2.9, 10.0].map(Duration::from_secs_f64).to_vec();
+        let options = TimingOptions { keyframes, ..Default::default() };
+        assert_eq!(events.process_timing(&options), 2);
+        // Keyframes after start and before end, keyframe before start. A short line is not snapped to nothing.
+        assert_eq!(times(&events), vec![(1100, 2900), (1100, 2000), (5000, 6000), (9950, 10050)]);
+    }
+}
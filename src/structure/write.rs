@@ -0,0 +1,225 @@
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::slice::Iter;
+use crate::structure::base_types::Color;
+use crate::structure::event::Event;
+use crate::structure::style::Style;
+use crate::structure::attachment::Attachment;
+use crate::structure::format::{ColumnOrder, Format, FormatKind};
+use crate::structure::{Ass, Events, ScriptCollisionsType, ScriptInfo, Styles, ToLine, UnparsedLine, EVENT_FORMAT, STYLE_FORMAT};
+
+impl Display for Color {
+    /// Writes color in `&HAABBGGRR` form used by styles.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "&H{:02X}{:02X}{:02X}{:02X}", self.a, self.b, self.g, self.r)
+    }
+}
+
+impl Style<'_> {
+    /// Value of `Style:` line in [`STYLE_FORMAT`] column order.
+    pub fn to_line(&self) -> String {
+        ToLine::to_line(self, STYLE_FORMAT)
+    }
+}
+
+impl Event<'_> {
+    /// Value of `Dialogue:`/`Comment:` line in [`EVENT_FORMAT`] column order.
+    pub fn to_line(&self) -> String {
+        ToLine::to_line(self, EVENT_FORMAT)
+    }
+}
+
+impl Display for ScriptInfo<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "[Script Info]")?;
+        if let Some(title) = self.title {
+            writeln!(f, "Title: {}", title)?;
+        }
+        let authors = &self.authors;
+        for (key, value) in [
+            ("Original Script", authors.script()),
+            ("Original Translation", authors.translation()),
+            ("Original Editing", authors.editing()),
+            ("Original Timing", authors.timing()),
+            ("Script Updated By", authors.updated_by()),
+            ("Update Details", authors.update_details()),
+            ("Sync Point", self.sync_point),
+        ] {
+            if let Some(value) = value {
+                writeln!(f, "{}: {}", key, value)?;
+            }
+        }
+        writeln!(f, "ScriptType: {}", self.version)?;
+        if let Some(x) = self.resolution.x {
+            writeln!(f, "PlayResX: {}", x)?;
+        }
+        if let Some(y) = self.resolution.y {
+            writeln!(f, "PlayResY: {}", y)?;
+        }
+        writeln!(f, "WrapStyle: {}", self.wrap_style as u8)?;
+        if let ScriptCollisionsType::Reverse = self.collisions {
+            writeln!(f, "Collisions: Reverse")?;
+        }
+        if self.timescale != 0.0 {
+            writeln!(f, "Timer: {:.4}", self.timescale)?;
+        }
+        for (key, value) in &self.other {
+            writeln!(f, "{}: {}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Format to write a section with. Unparsed lines are written as is, so they keep the section in its own format.
+fn write_format(format: &Option<Format>, unparsed: &[UnparsedLine], kind: FormatKind, order: ColumnOrder) -> String {
+    match format {
+        Some(format) if order == ColumnOrder::Preserved || !unparsed.is_empty() => format.to_string(),
+        _ => kind.format().to_string(),
+    }
+}
+
+/// Writes unparsed lines that come before item `position`.
+fn write_unparsed(f: &mut impl std::fmt::Write, lines: &mut Peekable<Iter<UnparsedLine>>, position: usize) -> std::fmt::Result {
+    while let Some(line) = lines.next_if(|x| x.position <= position) {
+        writeln!(f, "{}", line.text)?;
+    }
+    Ok(())
+}
+
+impl Styles<'_> {
+    /// Writes the section with columns in standard order or in the order of its `Format` line.
+    /// Sections with unparsed lines always keep their `Format` line.
+    pub fn write(&self, f: &mut impl std::fmt::Write, order: ColumnOrder) -> std::fmt::Result {
+        let format = write_format(&self.format, &self.unparsed, FormatKind::Styles, order);
+        writeln!(f, "[V4+ Styles]")?;
+        writeln!(f, "Format: {}", format)?;
+        let mut unparsed = self.unparsed.iter().peekable();
+        for (i, style) in self.styles.iter().enumerate() {
+            write_unparsed(f, &mut unparsed, i)?;
+            writeln!(f, "Style: {}", ToLine::to_line(style, &format))?;
+        }
+        write_unparsed(f, &mut unparsed, usize::MAX)
+    }
+}
+
+impl Display for Styles<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.write(f, ColumnOrder::Canonical)
+    }
+}
+
+impl Events<'_> {
+    /// Writes the section with columns in standard order or in the order of its `Format` line.
+    /// Sections with unparsed lines always keep their `Format` line.
+    pub fn write(&self, f: &mut impl std::fmt::Write, order: ColumnOrder) -> std::fmt::Result {
+        let format = write_format(&self.format, &self.unparsed, FormatKind::Events, order);
+        writeln!(f, "[Events]")?;
+        writeln!(f, "Format: {}", format)?;
+        let mut unparsed = self.unparsed.iter().peekable();
+        for (i, (key, event)) in self.events.iter().enumerate() {
+            write_unparsed(f, &mut unparsed, i)?;
+            writeln!(f, "{}: {}", key.as_str(), ToLine::to_line(event, &format))?;
+        }
+        write_unparsed(f, &mut unparsed, usize::MAX)
+    }
+}
+
+impl Display for Events<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.write(f, ColumnOrder::Canonical)
+    }
+}
+
+fn write_attachments(f: &mut impl std::fmt::Write, section: &str, key: &str, attachments: &[Attachment]) -> std::fmt::Result {
+    if attachments.is_empty() {
+        return Ok(());
+    }
+    writeln!(f, "\n[{}]", section)?;
+    for attachment in attachments {
+        writeln!(f, "{}: {}", key, attachment.name)?;
+        for line in &attachment.data {
+            writeln!(f, "{}", line)?;
+        }
+    }
+    Ok(())
+}
+
+impl Ass<'_> {
+    /// Writes known sections in standard order, then other sections unchanged. Event columns are in `order`.
+    pub fn write(&self, f: &mut impl std::fmt::Write, order: ColumnOrder) -> std::fmt::Result {
+        writeln!(f, "{}", self.info)?;
+        writeln!(f, "{}", self.styles)?;
+        self.events.write(f, order)?;
+        write_attachments(f, "Fonts", "fontname", &self.fonts)?;
+        write_attachments(f, "Graphics", "filename", &self.graphics)?;
+        for section in &self.other_sections {
+            writeln!(f, "\n[{}]", section.name)?;
+            for line in &section.lines {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Ass<'_> {
+    /// Writes script in canonical form: known sections in standard order with standard formats.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.write(f, ColumnOrder::Canonical)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    const SCRIPT: &str = "[Script Info]
+ScriptType: v4.00+
+PlayResX: 640
+PlayResY: 360
+WrapStyle: 0
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+Dialogue: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,a
+Dialogue: 0,0:00:01,0:00:02.00,Default,,0,0,0,,bad time
+Picture: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,x.png
+Comment: 0,0:00:02.00,0:00:03.00,Default,,0,0,0,,c
+Sound: 0,0:00:03.00,0:00:04.00,Default,,0,0,0,,x.wav
+
+[Aegisub Project Garbage]
+Audio File: a.wav
+; note
+";
+
+    #[test]
+    fn round_trip() {
+        let ass = crate::parse_str(SCRIPT).unwrap();
+        assert_eq!(ass.events.events.len(), 2);
+        assert_eq!(ass.events.unparsed.len(), 3);
+        assert_eq!(ass.events.unparsed[0].position, 1);
+        assert_eq!(ass.events.unparsed[2].position, 2);
+        assert_eq!(ass.other_sections[0].name, "Aegisub Project Garbage");
+        assert_eq!(ass.to_string(), SCRIPT);
+    }
+
+    #[test]
+    fn unparsed_styles_keep_format() {
+        let data = SCRIPT.replace("OutlineColour", "OutlineColor");
+        let ass = crate::parse_str(&data).unwrap();
+        assert!(ass.styles.styles.is_empty());
+        assert_eq!(ass.styles.unparsed.len(), 1);
+        assert_eq!(ass.to_string(), data);
+    }
+
+    #[test]
+    fn edited_events_keep_unparsed_lines() {
+        let mut ass = crate::parse_str(SCRIPT).unwrap();
+        ass.events.events.clear();
+        let written = ass.to_string();
+        assert!(written.contains("Dialogue: 0,0:00:01,0:00:02.00,Default,,0,0,0,,bad time\nPicture: "));
+        assert!(written.contains("x.wav\n\n[Aegisub Project Garbage]"));
+    }
+}
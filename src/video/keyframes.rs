@@ -0,0 +1,130 @@
+use std::time::Duration;
+use crate::structure::InvalidValue;
+use crate::video::FrameTimes;
+
+/// Frame numbers of scene changes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Keyframes {
+    /// Sorted and without duplicates.
+    pub frames: Vec<i64>,
+    /// Frame rate stored in the file, Aegisub keyframes only.
+    pub fps: Option<f64>,
+}
+
+impl Keyframes {
+    /// Parses keyframes in Aegisub format, XviD pass stats, x264 stats or a plain list of frame numbers.
+    /// Format is detected from the first line.
+    pub fn parse(s: &str) -> Result<Self, InvalidValue> {
+        let s = s.strip_prefix('\u{feff}').unwrap_or(s);
+        let first = s.lines().map(str::trim).find(|x| !x.is_empty()).unwrap_or("");
+        let mut keyframes = if first.starts_with("# keyframe format v1") {
+            parse_aegisub(s)?
+        } else if first.starts_with("# XviD 2pass stat file") {
+            parse_xvid(s)
+        } else if first.starts_with("#options:") {
+            parse_x264(s)?
+        } else {
+            parse_list(s)?
+        };
+        keyframes.frames.sort_unstable();
+        keyframes.frames.dedup();
+        Ok(keyframes)
+    }
+
+    /// Times events should start at to begin on keyframes, usable as [`crate::structure::timing::TimingOptions::keyframes`].
+    pub fn times(&self, frames: &FrameTimes) -> Vec<Duration> {
+        self.frames.iter().map(|x| frames.start_time(*x)).collect()
+    }
+}
+
+fn parse_frame(s: &str) -> Result<i64, InvalidValue> {
+    s.trim().parse::<i64>().ok().filter(|x| *x >= 0).ok_or(InvalidValue)
+}
+
+/// Lines other than blank ones and `#` comments.
+fn content(s: &str) -> impl Iterator<Item = &str> {
+    s.lines().map(str::trim).filter(|x| !x.is_empty() && !x.starts_with('#'))
+}
+
+fn parse_aegisub(s: &str) -> Result<Keyframes, InvalidValue> {
+    let mut keyframes = Keyframes::default();
+    for line in content(s) {
+        match line.strip_prefix("fps ") {
+            Some(fps) => keyframes.fps = Some(fps.trim().parse().map_err(|_| InvalidValue)?).filter(|x| *x > 0.0),
+            None => keyframes.frames.push(parse_frame(line)?),
+        }
+    }
+    Ok(keyframes)
+}
+
+/// Each frame is a line starting with its type, `i` for keyframes.
+fn parse_xvid(s: &str) -> Keyframes {
+    let mut keyframes = Keyframes::default();
+    let frames = content(s).filter(|x| x.starts_with(['i', 'p', 'b', 's', 'I', 'P', 'B', 'S']));
+    for (frame, line) in frames.enumerate() {
+        if line.starts_with(['i', 'I']) {
+            keyframes.frames.push(frame as i64);
+        }
+    }
+    keyframes
+}
+
+/// Lines like `in:0 out:0 type:I dur:2 ...`, where `I` and `i` are keyframes.
+fn parse_x264(s: &str) -> Result<Keyframes, InvalidValue> {
+    let mut keyframes = Keyframes::default();
+    for (count, line) in content(s).enumerate() {
+        let field = |name: &str| line.split_whitespace().find_map(|x| x.strip_prefix(name));
+        let Some(kind) = field("type:") else { continue };
+        if kind.starts_with(['I', 'i']) {
+            let frame = match field("in:") {
+                Some(x) => parse_frame(x)?,
+                None => count as i64,
+            };
+            keyframes.frames.push(frame);
+        }
+    }
+    Ok(keyframes)
+}
+
+fn parse_list(s: &str) -> Result<Keyframes, InvalidValue> {
+    let frames = content(s).map(parse_frame).collect::<Result<Vec<_>, _>>()?;
+    Ok(Keyframes { frames, fps: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aegisub() {
+        let keyframes = Keyframes::parse("# keyframe format v1\nfps 23.976\n0\n70\n26\n26\n").unwrap();
+        assert_eq!(keyframes.frames, vec![0, 26, 70]);
+        assert_eq!(keyframes.fps, Some(23.976));
+        assert!(Keyframes::parse("# keyframe format v1\nfps 0\nx\n").is_err());
+    }
+
+    #[test]
+    fn xvid() {
+        let data = "# XviD 2pass stat file\n# comment\ni 1 2\np 1 2\nb 1 2\nI 1 2\n";
+        assert_eq!(Keyframes::parse(data).unwrap().frames, vec![0, 3]);
+    }
+
+    #[test]
+    fn x264() {
+        let data = "#options: 1280x720\nin:0 out:0 type:I dur:2\nin:2 out:1 type:b dur:2\nin:1 out:2 type:P dur:2\nin:5 out:3 type:i dur:2\n";
+        assert_eq!(Keyframes::parse(data).unwrap().frames, vec![0, 5]);
+    }
+
+    #[test]
+    fn list() {
+        assert_eq!(Keyframes::parse("\u{feff}10\n\n5\n").unwrap().frames, vec![5, 10]);
+        assert!(Keyframes::parse("5\n-1\n").is_err());
+    }
+
+    #[test]
+    fn times() {
+        let keyframes = Keyframes::parse("0\n24\n").unwrap();
+        let frames = FrameTimes::constant(24.0).unwrap();
+        assert_eq!(keyframes.times(&frames), vec![Duration::ZERO, Duration::from_millis(980)]);
+    }
+}
@@ -0,0 +1,225 @@
+pub mod keyframes;
+
+use std::time::Duration;
+use crate::structure::InvalidValue;
+
+/// Tolerance for comparing frame timestamps with event times, in milliseconds.
+const EPSILON: f64 = 1e-6;
+
+/// Mapping between frame numbers and times for constant or variable frame rate video.
+///
+/// A frame is shown from its timestamp until the next one. An event is visible on a frame
+/// if `start <= timestamp < end`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameTimes {
+    Constant { fps: f64 },
+    /// Timestamps of frames in milliseconds. Frames before the first and after the last one
+    /// are extrapolated with `fps`.
+    Variable { timestamps: Vec<f64>, fps: f64 },
+}
+
+fn parse_f64(s: &str) -> Result<f64, InvalidValue> {
+    s.trim().parse::<f64>().ok().filter(|x| x.is_finite()).ok_or(InvalidValue)
+}
+
+impl FrameTimes {
+    pub fn constant(fps: f64) -> Result<Self, InvalidValue> {
+        if fps.is_finite() && fps > 0.0 {
+            Ok(FrameTimes::Constant { fps })
+        } else {
+            Err(InvalidValue)
+        }
+    }
+
+    /// Parses Matroska timecodes file, format v1 (frame ranges with frame rates) or v2 (timestamp of each frame).
+    pub fn parse_timecodes(s: &str) -> Result<Self, InvalidValue> {
+        let s = s.strip_prefix('\u{feff}').unwrap_or(s);
+        let mut lines = s.lines().map(str::trim).filter(|x| !x.is_empty());
+        let header = lines.next().ok_or(InvalidValue)?.to_ascii_lowercase();
+        let mut lines = lines.filter(|x| !x.starts_with('#'));
+        if header.starts_with("# timecode format v1") || header.starts_with("# timestamp format v1") {
+            let assume = lines.next().ok_or(InvalidValue)?;
+            let (key, fps) = assume.split_once([' ', '\t']).ok_or(InvalidValue)?;
+            if !key.eq_ignore_ascii_case("assume") {
+                return Err(InvalidValue);
+            }
+            let fps = parse_f64(fps)?;
+            if fps <= 0.0 {
+                return Err(InvalidValue);
+            }
+            let mut ranges = vec![];
+            for line in lines {
+                let mut parts = line.split(',');
+                let (Some(start), Some(end), Some(rate), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+                else {
+                    return Err(InvalidValue);
+                };
+                let start: usize = start.trim().parse().map_err(|_| InvalidValue)?;
+                let end: usize = end.trim().parse().map_err(|_| InvalidValue)?;
+                let rate = parse_f64(rate)?;
+                if end < start || rate <= 0.0 {
+                    return Err(InvalidValue);
+                }
+                ranges.push((start, end, rate));
+            }
+            ranges.sort_by_key(|x| x.0);
+            let frames = ranges.last().map(|x| x.1 + 1).unwrap_or(0);
+            let mut timestamps = Vec::with_capacity(frames + 1);
+            let mut time = 0.0;
+            let mut ranges = ranges.into_iter().peekable();
+            for frame in 0..=frames {
+                timestamps.push(time);
+                while ranges.next_if(|x| x.1 < frame).is_some() {}
+                let rate = match ranges.peek() {
+                    Some(&(start, _, rate)) if start <= frame => rate,
+                    _ => fps,
+                };
+                time += 1000.0 / rate;
+            }
+            Ok(FrameTimes::Variable { timestamps, fps })
+        } else if header.starts_with("# timecode format v2")
+            || header.starts_with("# timestamp format v2")
+            || header.starts_with("# timestamp format v4")
+        {
+            let timestamps = lines.map(parse_f64).collect::<Result<Vec<_>, _>>()?;
+            if timestamps.len() < 2 || timestamps.windows(2).any(|x| x[1] <= x[0]) {
+                return Err(InvalidValue);
+            }
+            let span = timestamps[timestamps.len() - 1] - timestamps[0];
+            let fps = (timestamps.len() - 1) as f64 * 1000.0 / span;
+            Ok(FrameTimes::Variable { timestamps, fps })
+        } else {
+            Err(InvalidValue)
+        }
+    }
+
+    /// Frame rate used for frames outside of known timestamps.
+    pub fn fps(&self) -> f64 {
+        match self {
+            FrameTimes::Constant { fps } | FrameTimes::Variable { fps, .. } => *fps,
+        }
+    }
+
+    /// Timestamp of the frame in milliseconds.
+    pub fn timestamp(&self, frame: i64) -> f64 {
+        match self {
+            FrameTimes::Constant { fps } => frame as f64 * 1000.0 / fps,
+            FrameTimes::Variable { timestamps, fps } => {
+                let last = timestamps.len() as i64 - 1;
+                if frame < 0 {
+                    timestamps[0] + frame as f64 * 1000.0 / fps
+                } else if frame > last {
+                    timestamps[last as usize] + (frame - last) as f64 * 1000.0 / fps
+                } else {
+                    timestamps[frame as usize]
+                }
+            }
+        }
+    }
+
+    /// Number of the last frame with timestamp below `ms`, or not above it if `inclusive`.
+    fn last_frame(&self, ms: f64, inclusive: bool) -> i64 {
+        let ms = if inclusive { ms + EPSILON } else { ms - EPSILON };
+        // Frames are counted from `origin` at `fps` outside of known timestamps.
+        let extrapolate = |origin: i64, ms: f64| origin + ((ms - self.timestamp(origin)) * self.fps() / 1000.0).floor() as i64;
+        match self {
+            FrameTimes::Constant { .. } => extrapolate(0, ms),
+            FrameTimes::Variable { timestamps, .. } => {
+                let count = timestamps.partition_point(|x| *x < ms);
+                if count == 0 {
+                    extrapolate(0, ms)
+                } else if count == timestamps.len() {
+                    extrapolate(count as i64 - 1, ms)
+                } else {
+                    count as i64 - 1
+                }
+            }
+        }
+    }
+
+    /// Frame shown at `time`.
+    pub fn frame_at(&self, time: Duration) -> i64 {
+        self.last_frame(time.as_secs_f64() * 1000.0, true)
+    }
+
+    /// First frame of an event starting at `time`.
+    pub fn start_frame(&self, time: Duration) -> i64 {
+        self.last_frame(time.as_secs_f64() * 1000.0, false) + 1
+    }
+
+    /// Last frame of an event ending at `time`. Less than [`FrameTimes::start_frame`] for events shorter than a frame.
+    pub fn end_frame(&self, time: Duration) -> i64 {
+        self.last_frame(time.as_secs_f64() * 1000.0, false)
+    }
+
+    /// Event start time, in whole centiseconds, that makes event start on `frame`.
+    ///
+    /// Any time after the previous frame's timestamp up to this frame's one works, the middle
+    /// of that range is used so small rounding differences of players don't matter.
+    pub fn start_time(&self, frame: i64) -> Duration {
+        let prev = self.timestamp(frame - 1);
+        let current = self.timestamp(frame);
+        let in_range = |cs: f64| cs > prev + EPSILON && cs <= current + EPSILON;
+        let mut cs = ((prev + current) / 20.0).round() * 10.0;
+        if !in_range(cs) {
+            cs = (current / 10.0 + EPSILON).floor() * 10.0;
+        }
+        Duration::from_millis(cs.max(0.0) as u64)
+    }
+
+    /// Event end time, in whole centiseconds, that makes `frame` the last one event is shown on.
+    pub fn end_time(&self, frame: i64) -> Duration {
+        self.start_time(frame + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant() {
+        let frames = FrameTimes::constant(24000.0 / 1001.0).unwrap();
+        assert_eq!(frames.frame_at(Duration::from_millis(1000)), 23);
+        assert_eq!(frames.start_frame(Duration::from_millis(1000)), 24);
+        assert_eq!(frames.end_frame(Duration::from_millis(1000)), 23);
+        assert_eq!(frames.start_time(24), Duration::from_millis(980));
+        assert_eq!(frames.end_time(23), Duration::from_millis(980));
+        assert!(FrameTimes::constant(0.0).is_err());
+        assert!(FrameTimes::constant(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn exact_timestamp() {
+        let frames = FrameTimes::constant(25.0).unwrap();
+        // Frame 25 starts exactly at 1s, so an event starting then starts on it.
+        assert_eq!(frames.frame_at(Duration::from_millis(1000)), 25);
+        assert_eq!(frames.start_frame(Duration::from_millis(1000)), 25);
+        assert_eq!(frames.end_frame(Duration::from_millis(1000)), 24);
+    }
+
+    #[test]
+    fn timecodes_v1() {
+        let data = "# timecode format v1\nAssume 25\n# comment\n2,3,50\n";
+        let frames = FrameTimes::parse_timecodes(data).unwrap();
+        let FrameTimes::Variable { timestamps, fps } = &frames else { panic!() };
+        assert_eq!(*fps, 25.0);
+        assert_eq!(timestamps, &vec![0.0, 40.0, 80.0, 100.0, 120.0]);
+        assert_eq!(frames.timestamp(5), 160.0);
+        assert_eq!(frames.timestamp(-1), -40.0);
+        assert!(FrameTimes::parse_timecodes("# timecode format v1\n2,3,50\n").is_err());
+        assert!(FrameTimes::parse_timecodes("# timecode format v1\nAssume 25\n3,2,50\n").is_err());
+    }
+
+    #[test]
+    fn timecodes_v2() {
+        let data = "\u{feff}# timestamp format v2\n0\n40\n100\n140\n";
+        let frames = FrameTimes::parse_timecodes(data).unwrap();
+        assert_eq!(frames.fps(), 3.0 * 1000.0 / 140.0);
+        assert_eq!(frames.frame_at(Duration::from_millis(99)), 1);
+        assert_eq!(frames.frame_at(Duration::from_millis(100)), 2);
+        assert_eq!(frames.start_time(2), Duration::from_millis(70));
+        assert!(FrameTimes::parse_timecodes("# timecode format v2\n0\n40\n40\n").is_err());
+        assert!(FrameTimes::parse_timecodes("# timecode format v3\n0\n").is_err());
+    }
+}